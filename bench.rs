@@ -53,7 +53,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Build both binaries first
     println!("Building binaries...");
     let build_output = Command::new("cargo")
-        .args(&["build", "--release", "--bin", "enum_version"])
+        .args(["build", "--release", "--bin", "enum_version"])
         .output()?;
 
     if !build_output.status.success() {
@@ -65,7 +65,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     let build_output = Command::new("cargo")
-        .args(&["build", "--release", "--bin", "trait_version"])
+        .args(["build", "--release", "--bin", "trait_version"])
         .output()?;
 
     if !build_output.status.success() {
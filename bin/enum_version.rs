@@ -1,15 +1,57 @@
-use brainfuck::Interpreter;
+use brainfuck::{Cell, EofPolicy, Interpreter, MemoryOptions, StdIo, TapeMode, DEFAULT_TAPE_LEN};
 use std::env;
 
+/// Parses `--eof=zero|ones|unchanged`, defaulting to `LeaveUnchanged` when
+/// absent or unrecognized.
+fn parse_eof_policy(args: &[String]) -> EofPolicy {
+    match args.iter().find_map(|a| a.strip_prefix("--eof=")) {
+        Some("zero") => EofPolicy::Zero,
+        Some("ones") => EofPolicy::AllOnes,
+        _ => EofPolicy::LeaveUnchanged,
+    }
+}
+
+/// Parses `--tape=growable` or `--tape=fixed:<n>`, defaulting to the
+/// classic fixed 30,000-cell tape when absent or unrecognized.
+fn parse_tape_mode(args: &[String]) -> TapeMode {
+    match args.iter().find_map(|a| a.strip_prefix("--tape=")) {
+        Some("growable") => TapeMode::Growable,
+        Some(spec) => match spec.strip_prefix("fixed:").and_then(|n| n.parse().ok()) {
+            Some(n) => TapeMode::Fixed(n),
+            None => TapeMode::Fixed(DEFAULT_TAPE_LEN),
+        },
+        None => TapeMode::Fixed(DEFAULT_TAPE_LEN),
+    }
+}
+
+fn run_with_cell<T: Cell>(
+    content: String,
+    options: MemoryOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = Interpreter::<T>::with_options(content, options);
+    let mut stdin = StdIo;
+    let mut stdout = StdIo;
+    interpreter.run(&mut stdin, &mut stdout)?;
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let filepath = env::args().nth(1).unwrap_or_else(|| "1.bf".to_string());
+    let args: Vec<String> = env::args().collect();
+    let filepath = args.get(1).cloned().unwrap_or_else(|| "1.bf".to_string());
     let filename = env::current_dir()?.join(filepath);
     let content = std::fs::read_to_string(filename)?;
-    let mut interpreter = Interpreter::new(content);
 
-    let time = std::time::Instant::now();
+    let options = MemoryOptions {
+        tape: parse_tape_mode(&args),
+        eof_policy: parse_eof_policy(&args),
+    };
 
-    interpreter.run();
+    let time = std::time::Instant::now();
+    match args.iter().find_map(|a| a.strip_prefix("--cell=")) {
+        Some("u16") => run_with_cell::<u16>(content, options)?,
+        Some("u32") => run_with_cell::<u32>(content, options)?,
+        _ => run_with_cell::<u8>(content, options)?,
+    }
     println!("Finished in {}ms", time.elapsed().as_millis());
     Ok(())
 }
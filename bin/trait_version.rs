@@ -1,427 +1,535 @@
-use brainfuck::Memory;
+use brainfuck::{BrainfuckError, Cell, EofPolicy, Memory, MemoryOptions, StdIo, TapeMode, DEFAULT_TAPE_LEN};
 use std::env;
 use std::io::Write;
 
-// Define the Instruction trait
-trait Instruction: std::fmt::Debug {
-    fn run_effect(&self, memory: &mut Memory);
-    fn as_any(&self) -> &dyn std::any::Any;
-}
-
-#[derive(Debug)]
-struct IncStruct(u32);
-impl Instruction for IncStruct {
-    fn run_effect(&self, memory: &mut Memory) {
-        memory.increment_cell(self.0);
-    }
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
-}
-
+/// Pre-bytecode optimized tree. Unlike the boxed `dyn Instruction` design
+/// this replaces, `Add`/`Move` already carry a signed amount/offset so the
+/// peephole passes below can fold an "offset there, op, offset back" triple
+/// into a single node without any `downcast_ref`.
 #[derive(Debug)]
-struct DecStruct(u32);
-impl Instruction for DecStruct {
-    fn run_effect(&self, memory: &mut Memory) {
-        memory.decrement_cell(self.0);
-    }
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
+enum Inst {
+    Add { offset: i32, amount: i32 },
+    Move(i32),
+    Out,
+    In,
+    SetZero,
+    JumpOut(i32),
+    MulAdd { offset: i32, factor: i32 },
+    Loop { body: Vec<Inst>, scaled: bool },
 }
 
+/// Flat bytecode form of `Inst`. Every `Loop` lowers to a `JumpIfZero`/
+/// `JumpIfNonZero` pair with absolute, pre-resolved targets; a `scaled`
+/// loop (pure arithmetic, net pointer movement zero) lowers to `Scaled`
+/// instead, so the VM runs it once with every `Add` multiplied by the
+/// loop counter rather than looping at all.
 #[derive(Debug)]
-struct MoveRightStruct(u32);
-impl Instruction for MoveRightStruct {
-    fn run_effect(&self, memory: &mut Memory) {
-        memory.move_pointer_right(self.0);
-    }
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
+enum Op {
+    Add { offset: i32, amount: i8 },
+    Move(i32),
+    Out,
+    In,
+    SetZero,
+    MulAdd { offset: i32, factor: u8 },
+    JumpIfZero(usize),
+    JumpIfNonZero(usize),
+    Scaled(Vec<Op>),
 }
 
-#[derive(Debug)]
-struct MoveLeftStruct(u32);
-impl Instruction for MoveLeftStruct {
-    fn run_effect(&self, memory: &mut Memory) {
-        memory.move_pointer_left(self.0);
-    }
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
+/// Lowers an optimized `Inst` tree into `ops`, appending opcodes in order.
+fn compile(insts: &[Inst], ops: &mut Vec<Op>) {
+    for inst in insts {
+        match inst {
+            Inst::Add { offset, amount } => ops.push(Op::Add {
+                offset: *offset,
+                amount: *amount as i8,
+            }),
+            Inst::Move(n) => ops.push(Op::Move(*n)),
+            Inst::Out => ops.push(Op::Out),
+            Inst::In => ops.push(Op::In),
+            Inst::SetZero => ops.push(Op::SetZero),
+            Inst::MulAdd { offset, factor } => ops.push(Op::MulAdd {
+                offset: *offset,
+                factor: *factor as u8,
+            }),
+            Inst::JumpOut(n) => {
+                let jump_if_zero = ops.len();
+                ops.push(Op::JumpIfZero(0)); // patched below
+                let start = ops.len();
+                ops.push(Op::Move(*n));
+                ops.push(Op::JumpIfNonZero(start));
+                let end = ops.len();
+                ops[jump_if_zero] = Op::JumpIfZero(end);
+            }
+            Inst::Loop { body, scaled: false } => {
+                let jump_if_zero = ops.len();
+                ops.push(Op::JumpIfZero(0)); // patched below
+                let start = ops.len();
+                compile(body, ops);
+                ops.push(Op::JumpIfNonZero(start));
+                let end = ops.len();
+                ops[jump_if_zero] = Op::JumpIfZero(end);
+            }
+            Inst::Loop { body, scaled: true } => {
+                let mut inner = Vec::with_capacity(body.len());
+                compile(body, &mut inner);
+                ops.push(Op::Scaled(inner));
+            }
+        }
     }
 }
 
-#[derive(Debug)]
-struct LoopStruct(Vec<Box<dyn Instruction>>, bool);
-impl Instruction for LoopStruct {
-    fn run_effect(&self, memory: &mut Memory) {
-        match self.1 {
-            false => {
-                while memory.val() != 0 {
-                    self.0.iter().for_each(|e| e.run_effect(memory));
+/// Executes a flat `Op` program with a single `pc`-driven loop, the way a
+/// bytecode VM dispatches over a resolved instruction stream, instead of
+/// walking a tree of boxed trait objects.
+fn run_bytecode<T: Cell>(ops: &[Op], memory: &mut Memory<'_, T>) -> Result<(), BrainfuckError> {
+    let mut pc = 0;
+    while pc < ops.len() {
+        match &ops[pc] {
+            Op::Add { offset, amount } => memory.add_at(*offset, *amount as i32)?,
+            Op::Move(n) => move_pointer(memory, *n)?,
+            Op::Out => memory.output_cell(),
+            Op::In => memory.input_cell(),
+            Op::SetZero => memory.cells[memory.pointer] = T::ZERO,
+            Op::MulAdd { offset, factor } => {
+                memory.offset_make_zero(*offset, *factor as i32)?;
+            }
+            Op::JumpIfZero(target) => {
+                if memory.val() == T::ZERO {
+                    pc = *target;
+                    continue;
+                }
+            }
+            Op::JumpIfNonZero(target) => {
+                if memory.val() != T::ZERO {
+                    pc = *target;
+                    continue;
                 }
             }
-            true if memory.val() != 0 => {
-                let times = memory.val() as u32;
-                self.0.iter().for_each(|e| {
-                    if let Some(inc) = e.as_any().downcast_ref::<IncStruct>() {
-                        memory.increment_cell(inc.0 * times);
-                    } else if let Some(dec) = e.as_any().downcast_ref::<DecStruct>() {
-                        memory.decrement_cell(dec.0 * times);
-                    } else if let Some(left) = e.as_any().downcast_ref::<MoveLeftStruct>() {
-                        memory.move_pointer_left(left.0);
-                    } else if let Some(right) = e.as_any().downcast_ref::<MoveRightStruct>() {
-                        memory.move_pointer_right(right.0);
+            Op::Scaled(body) => {
+                let times = memory.val().to_u32() as i32;
+                if times != 0 {
+                    for op in body {
+                        match op {
+                            Op::Add { offset, amount } => {
+                                memory.add_at(*offset, (*amount as i32) * times)?;
+                            }
+                            Op::Move(n) => move_pointer(memory, *n)?,
+                            _ => unreachable!(),
+                        }
                     }
-                });
+                }
             }
-            true => return,
         }
+        pc += 1;
     }
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
+    Ok(())
 }
 
-#[derive(Debug)]
-struct InputStruct;
-impl Instruction for InputStruct {
-    fn run_effect(&self, memory: &mut Memory) {
-        memory.input_cell()
-    }
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
+#[inline(always)]
+fn move_pointer<T: Cell>(memory: &mut Memory<'_, T>, n: i32) -> Result<(), BrainfuckError> {
+    if n >= 0 {
+        memory.move_pointer_right(n as u32)
+    } else {
+        memory.move_pointer_left((-n) as u32)
     }
 }
 
+/// A jump whose target falls outside the stream it came from, surfaced
+/// instead of panicking on an out-of-bounds index while disassembling.
+#[cfg(feature = "disasm")]
 #[derive(Debug)]
-struct OutputStruct;
-impl Instruction for OutputStruct {
-    fn run_effect(&self, memory: &mut Memory) {
-        memory.output_cell()
-    }
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
+enum DisasmError {
+    JumpTargetOutOfRange { pc: usize, target: usize },
 }
 
-#[derive(Debug)]
-struct MakeZeroStruct;
-impl Instruction for MakeZeroStruct {
-    fn run_effect(&self, memory: &mut Memory) {
-        memory.cells[memory.pointer] = 0;
-    }
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
+#[cfg(feature = "disasm")]
+impl std::fmt::Display for DisasmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisasmError::JumpTargetOutOfRange { pc, target } => {
+                write!(f, "instruction {pc:04} jumps to out-of-range target {target:04}")
+            }
+        }
     }
 }
 
-#[derive(Debug)]
-struct JumpOutStruct(Box<dyn Instruction>);
-impl Instruction for JumpOutStruct {
-    fn run_effect(&self, memory: &mut Memory) {
-        while memory.val() != 0 {
-            if let Some(left) = self.0.as_any().downcast_ref::<MoveLeftStruct>() {
-                memory.move_pointer_left(left.0);
-            } else if let Some(right) = self.0.as_any().downcast_ref::<MoveRightStruct>() {
-                memory.move_pointer_right(right.0);
+#[cfg(feature = "disasm")]
+impl std::error::Error for DisasmError {}
+
+/// Renders a flat `Op` stream as canonical, line-oriented text — one
+/// `index  mnemonic` line per instruction, jump targets resolved to the
+/// index they point at (e.g. `0005  JumpIfNonZero -> 0001`) — replacing
+/// the old `dev` mode's raw `{:#?}` dump of the pre-bytecode tree.
+#[cfg(feature = "disasm")]
+fn disasm(ops: &[Op]) -> Result<String, DisasmError> {
+    let mut out = String::new();
+    for (pc, op) in ops.iter().enumerate() {
+        if let Op::JumpIfZero(target) | Op::JumpIfNonZero(target) = op {
+            if *target > ops.len() {
+                return Err(DisasmError::JumpTargetOutOfRange { pc, target: *target });
             }
         }
+        out.push_str(&format!("{pc:04}  {}\n", disasm_op(op)));
     }
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
+    Ok(out)
 }
 
-#[derive(Debug)]
-struct OffsetOpStruct(i32, i32);
-impl Instruction for OffsetOpStruct {
-    fn run_effect(&self, memory: &mut Memory) {
-        memory.cells[memory.pointer.wrapping_add(self.0 as usize)] += self.1 as u8;
-    }
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
+#[cfg(feature = "disasm")]
+fn disasm_op(op: &Op) -> String {
+    match op {
+        Op::Add { offset, amount } => format!("Add off={offset:+} amount={amount:+}"),
+        Op::Move(n) => format!("Move {n:+}"),
+        Op::Out => "Out".to_string(),
+        Op::In => "In".to_string(),
+        Op::SetZero => "SetZero".to_string(),
+        Op::MulAdd { offset, factor } => format!("MulAdd off={offset:+} factor={factor}"),
+        Op::JumpIfZero(target) => format!("JumpIfZero -> {target:04}"),
+        Op::JumpIfNonZero(target) => format!("JumpIfNonZero -> {target:04}"),
+        Op::Scaled(body) => {
+            let rendered: Vec<String> = body.iter().map(disasm_op).collect();
+            format!("Scaled {{ {} }}", rendered.join("; "))
+        }
     }
 }
 
-#[derive(Debug)]
-struct OffsetMakeZeroOpStruct(i32, i32);
-impl Instruction for OffsetMakeZeroOpStruct {
-    fn run_effect(&self, memory: &mut Memory) {
-        let current_value = memory.val();
-        if current_value != 0 {
-            memory.cells[memory.pointer] = 0;
-            memory.cells[memory.pointer.wrapping_add(self.0 as usize)] +=
-                (self.1 as u8).wrapping_mul(current_value);
+/// Reconstructs `.bf` source equivalent to `insts`, expanding folded nodes
+/// (`SetZero`, `JumpOut`, `MulAdd`, a `scaled` `Loop`) back to the raw
+/// token sequence they stand in for, so a user can diff this against the
+/// original source to see what the optimizer changed.
+#[cfg(feature = "disasm")]
+fn reconstruct_bf(insts: &[Inst]) -> String {
+    let mut out = String::new();
+    reconstruct_bf_into(insts, &mut out);
+    out
+}
+
+#[cfg(feature = "disasm")]
+fn reconstruct_bf_into(insts: &[Inst], out: &mut String) {
+    for inst in insts {
+        match inst {
+            Inst::Add { offset, amount } => {
+                push_moves(out, *offset);
+                push_repeated(out, if *amount >= 0 { '+' } else { '-' }, amount.unsigned_abs());
+                push_moves(out, -*offset);
+            }
+            Inst::Move(n) => push_moves(out, *n),
+            Inst::Out => out.push('.'),
+            Inst::In => out.push(','),
+            Inst::SetZero => out.push_str("[-]"),
+            Inst::JumpOut(n) => {
+                out.push('[');
+                push_moves(out, *n);
+                out.push(']');
+            }
+            Inst::MulAdd { offset, factor } => {
+                out.push_str("[-");
+                push_moves(out, *offset);
+                push_repeated(out, if *factor >= 0 { '+' } else { '-' }, factor.unsigned_abs());
+                push_moves(out, -*offset);
+                out.push(']');
+            }
+            Inst::Loop { body, scaled: _ } => {
+                out.push('[');
+                reconstruct_bf_into(body, out);
+                out.push(']');
+            }
         }
     }
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
+}
+
+#[cfg(feature = "disasm")]
+fn push_moves(out: &mut String, n: i32) {
+    push_repeated(out, if n >= 0 { '>' } else { '<' }, n.unsigned_abs());
+}
+
+#[cfg(feature = "disasm")]
+fn push_repeated(out: &mut String, ch: char, count: u32) {
+    for _ in 0..count {
+        out.push(ch);
     }
 }
 
 struct Interpreter {
     source: String,
-    instructions: Vec<Box<dyn Instruction>>,
+    ast: Vec<Inst>,
 }
 
 impl Interpreter {
     fn new(source: String) -> Self {
         Self {
             source,
-            instructions: Vec::new(),
+            ast: Vec::new(),
         }
     }
 
-    fn optimize(mut instructions: Vec<Box<dyn Instruction>>) -> Box<dyn Instruction> {
-        loop {
-            match instructions.len() {
-                0 => {
-                    eprintln!("Empty loop detected");
-                    std::process::exit(1);
-                }
+    fn optimize(mut insts: Vec<Inst>) -> Result<Inst, BrainfuckError> {
+        let inst = loop {
+            match insts.len() {
+                0 => return Err(BrainfuckError::EmptyLoop),
                 1 => {
-                    let inst = instructions.into_iter().next().unwrap();
-                    if inst.as_any().downcast_ref::<DecStruct>().is_some()
-                        || inst.as_any().downcast_ref::<IncStruct>().is_some()
-                    {
-                        return Box::new(MakeZeroStruct);
-                    } else if inst.as_any().downcast_ref::<MoveLeftStruct>().is_some()
-                        || inst.as_any().downcast_ref::<MoveRightStruct>().is_some()
-                    {
-                        return Box::new(JumpOutStruct(inst));
-                    } else {
-                        eprintln!("Infinite loop of IO operations detected");
-                        std::process::exit(1);
-                    }
+                    break match insts.into_iter().next().unwrap() {
+                        Inst::Add { offset: 0, amount } if amount != 0 => Inst::SetZero,
+                        Inst::Move(n) => Inst::JumpOut(n),
+                        _ => return Err(BrainfuckError::InfiniteIoLoop),
+                    };
                 }
                 2 => {
-                    let first = &instructions[0];
-                    let second = &instructions[1];
-
-                    if let (Some(dec), Some(offset)) = (
-                        first.as_any().downcast_ref::<DecStruct>(),
-                        second.as_any().downcast_ref::<OffsetOpStruct>(),
-                    ) {
-                        if dec.0 == 1 {
-                            return Box::new(OffsetMakeZeroOpStruct(offset.0, offset.1));
-                        }
-                    }
-
-                    if let (Some(offset), Some(dec)) = (
-                        first.as_any().downcast_ref::<OffsetOpStruct>(),
-                        second.as_any().downcast_ref::<DecStruct>(),
-                    ) {
-                        if dec.0 == 1 {
-                            return Box::new(OffsetMakeZeroOpStruct(offset.0, offset.1));
-                        }
-                    }
-
-                    return Box::new(LoopStruct(instructions, false));
+                    break match <[Inst; 2]>::try_from(insts) {
+                        Ok(
+                            [Inst::Add {
+                                offset: 0,
+                                amount: -1,
+                            }, Inst::Add {
+                                offset,
+                                amount: factor,
+                            }]
+                            | [Inst::Add {
+                                offset,
+                                amount: factor,
+                            }, Inst::Add {
+                                offset: 0,
+                                amount: -1,
+                            }],
+                        ) if offset != 0 => Inst::MulAdd { offset, factor },
+                        Ok(arr) => Inst::Loop {
+                            body: arr.into(),
+                            scaled: false,
+                        },
+                        Err(insts) => Inst::Loop {
+                            body: insts,
+                            scaled: false,
+                        },
+                    };
                 }
                 3.. => {
-                    let mut offset: i32 = 0;
+                    let mut offset = 0i32;
                     let mut jump_out = false;
-                    for inst in instructions.iter() {
-                        if let Some(left) = inst.as_any().downcast_ref::<MoveLeftStruct>() {
-                            offset -= left.0 as i32;
-                        } else if let Some(right) = inst.as_any().downcast_ref::<MoveRightStruct>()
-                        {
-                            offset += right.0 as i32;
-                        } else if inst.as_any().downcast_ref::<IncStruct>().is_some()
-                            || inst.as_any().downcast_ref::<DecStruct>().is_some()
-                        {
-                            // Continue
-                        } else {
-                            jump_out = true;
-                            break;
+                    for inst in insts.iter() {
+                        match inst {
+                            Inst::Move(n) => offset += n,
+                            Inst::Add { offset: 0, .. } => {}
+                            _ => {
+                                jump_out = true;
+                                break;
+                            }
                         }
                     }
                     if !jump_out && offset == 0 {
-                        return Box::new(LoopStruct(instructions, true));
+                        break Inst::Loop {
+                            body: insts,
+                            scaled: true,
+                        };
                     }
 
                     let mut i = 0;
                     let mut matched = false;
-                    while i + 2 < instructions.len() {
-                        let should_replace = if let (Some(left), Some(dec), Some(right)) = (
-                            instructions[i].as_any().downcast_ref::<MoveLeftStruct>(),
-                            instructions[i + 1].as_any().downcast_ref::<DecStruct>(),
-                            instructions[i + 2]
-                                .as_any()
-                                .downcast_ref::<MoveRightStruct>(),
-                        ) {
-                            if left.0 == right.0 {
-                                Some(Box::new(OffsetOpStruct(-(left.0 as i32), -(dec.0 as i32)))
-                                    as Box<dyn Instruction>)
-                            } else {
-                                None
-                            }
-                        } else if let (Some(left), Some(inc), Some(right)) = (
-                            instructions[i].as_any().downcast_ref::<MoveLeftStruct>(),
-                            instructions[i + 1].as_any().downcast_ref::<IncStruct>(),
-                            instructions[i + 2]
-                                .as_any()
-                                .downcast_ref::<MoveRightStruct>(),
-                        ) {
-                            if left.0 == right.0 {
-                                Some(Box::new(OffsetOpStruct(-(left.0 as i32), inc.0 as i32))
-                                    as Box<dyn Instruction>)
-                            } else {
-                                None
-                            }
-                        } else if let (Some(right), Some(dec), Some(left)) = (
-                            instructions[i].as_any().downcast_ref::<MoveRightStruct>(),
-                            instructions[i + 1].as_any().downcast_ref::<DecStruct>(),
-                            instructions[i + 2]
-                                .as_any()
-                                .downcast_ref::<MoveLeftStruct>(),
-                        ) {
-                            if right.0 == left.0 {
-                                Some(Box::new(OffsetOpStruct(right.0 as i32, -(dec.0 as i32)))
-                                    as Box<dyn Instruction>)
-                            } else {
-                                None
-                            }
-                        } else if let (Some(right), Some(inc), Some(left)) = (
-                            instructions[i].as_any().downcast_ref::<MoveRightStruct>(),
-                            instructions[i + 1].as_any().downcast_ref::<IncStruct>(),
-                            instructions[i + 2]
-                                .as_any()
-                                .downcast_ref::<MoveLeftStruct>(),
-                        ) {
-                            if right.0 == left.0 {
-                                Some(Box::new(OffsetOpStruct(right.0 as i32, inc.0 as i32))
-                                    as Box<dyn Instruction>)
-                            } else {
-                                None
+                    while i + 2 < insts.len() {
+                        if let [Inst::Move(x), Inst::Add { offset: 0, amount }, Inst::Move(y)] =
+                            &insts[i..i + 3]
+                        {
+                            if *x != 0 && *x == -*y {
+                                let op = Inst::Add {
+                                    offset: *x,
+                                    amount: *amount,
+                                };
+                                insts.splice(i..i + 3, [op]);
+                                matched = true;
                             }
-                        } else {
-                            None
-                        };
-
-                        if let Some(replacement) = should_replace {
-                            instructions.splice(i..i + 3, [replacement]);
-                            matched = true;
                         }
                         i += 1;
                     }
                     if matched {
                         continue;
                     } else {
-                        return Box::new(LoopStruct(instructions, false));
+                        break Inst::Loop {
+                            body: insts,
+                            scaled: false,
+                        };
                     }
                 }
             }
-        }
+        };
+        Ok(inst)
     }
 
-    fn run(&mut self) {
-        self.instructions = self.parse();
+    fn run<T: Cell>(&mut self, options: MemoryOptions) -> Result<(), BrainfuckError> {
+        self.ast = self.parse()?;
         let args = std::env::args().collect::<Vec<String>>();
         if args.iter().any(|s| s == "dev") {
             let filename = (args[1].split(".").next().unwrap()).to_string() + ".txt";
-            let mut file = std::fs::File::create(filename).unwrap();
-            writeln!(file, "{:#?}", self.instructions).unwrap();
+            let mut file = std::fs::File::create(filename)?;
+            #[cfg(feature = "disasm")]
+            {
+                let mut dev_ops = Vec::new();
+                compile(&self.ast, &mut dev_ops);
+                match disasm(&dev_ops) {
+                    Ok(text) => write!(file, "{text}")?,
+                    Err(e) => writeln!(file, "; disasm error: {e}")?,
+                }
+                writeln!(file, "\n; reconstructed source:\n{}", reconstruct_bf(&self.ast))?;
+            }
+            #[cfg(not(feature = "disasm"))]
+            writeln!(file, "{:#?}", self.ast)?;
         }
 
-        let mut memory = Memory::new();
-        self.instructions
-            .iter()
-            .for_each(|inst| inst.run_effect(&mut memory));
+        let mut ops = Vec::new();
+        compile(&self.ast, &mut ops);
+
+        let mut stdin = StdIo;
+        let mut stdout = StdIo;
+        let mut memory = Memory::<T>::with_options(&mut stdin, &mut stdout, options);
+        run_bytecode(&ops, &mut memory)?;
         memory.flush();
+        Ok(())
     }
 
-    fn parse(&mut self) -> Vec<Box<dyn Instruction>> {
+    fn parse(&mut self) -> Result<Vec<Inst>, BrainfuckError> {
         use brainfuck::Token::{self, *};
-        let mut loop_stack: Vec<Vec<Box<dyn Instruction>>> = Vec::new();
-        let mut current_instructions: Vec<Box<dyn Instruction>> = Vec::new();
+        let mut loop_stack: Vec<Vec<Inst>> = Vec::new();
+        let mut current: Vec<Inst> = Vec::new();
 
         for (i, c) in self.source.chars().enumerate() {
             match Token::from_char(c) {
-                Plus => {
-                    if let Some(last) = current_instructions.last_mut() {
-                        if let Some(inc) = last.as_any().downcast_ref::<IncStruct>() {
-                            let new_count = inc.0 + 1;
-                            *last = Box::new(IncStruct(new_count));
-                        } else {
-                            current_instructions.push(Box::new(IncStruct(1)));
-                        }
-                    } else {
-                        current_instructions.push(Box::new(IncStruct(1)));
-                    }
-                }
-                Minus => {
-                    if let Some(last) = current_instructions.last_mut() {
-                        if let Some(dec) = last.as_any().downcast_ref::<DecStruct>() {
-                            let new_count = dec.0 + 1;
-                            *last = Box::new(DecStruct(new_count));
-                        } else {
-                            current_instructions.push(Box::new(DecStruct(1)));
-                        }
-                    } else {
-                        current_instructions.push(Box::new(DecStruct(1)));
-                    }
-                }
-                Right => {
-                    if let Some(last) = current_instructions.last_mut() {
-                        if let Some(right) = last.as_any().downcast_ref::<MoveRightStruct>() {
-                            let new_count = right.0 + 1;
-                            *last = Box::new(MoveRightStruct(new_count));
-                        } else {
-                            current_instructions.push(Box::new(MoveRightStruct(1)));
-                        }
-                    } else {
-                        current_instructions.push(Box::new(MoveRightStruct(1)));
-                    }
-                }
-                Left => {
-                    if let Some(last) = current_instructions.last_mut() {
-                        if let Some(left) = last.as_any().downcast_ref::<MoveLeftStruct>() {
-                            let new_count = left.0 + 1;
-                            *last = Box::new(MoveLeftStruct(new_count));
-                        } else {
-                            current_instructions.push(Box::new(MoveLeftStruct(1)));
-                        }
-                    } else {
-                        current_instructions.push(Box::new(MoveLeftStruct(1)));
-                    }
-                }
-                Dot => current_instructions.push(Box::new(OutputStruct)),
-                Comma => current_instructions.push(Box::new(InputStruct)),
+                Plus => match current.last_mut() {
+                    Some(Inst::Add { offset: 0, amount }) => *amount += 1,
+                    _ => current.push(Inst::Add {
+                        offset: 0,
+                        amount: 1,
+                    }),
+                },
+                Minus => match current.last_mut() {
+                    Some(Inst::Add { offset: 0, amount }) => *amount -= 1,
+                    _ => current.push(Inst::Add {
+                        offset: 0,
+                        amount: -1,
+                    }),
+                },
+                Right => match current.last_mut() {
+                    Some(Inst::Move(n)) if *n > 0 => *n += 1,
+                    _ => current.push(Inst::Move(1)),
+                },
+                Left => match current.last_mut() {
+                    Some(Inst::Move(n)) if *n < 0 => *n -= 1,
+                    _ => current.push(Inst::Move(-1)),
+                },
+                Dot => current.push(Inst::Out),
+                Comma => current.push(Inst::In),
                 BracketOpen => {
-                    loop_stack.push(current_instructions);
-                    current_instructions = Vec::new();
+                    loop_stack.push(current);
+                    current = Vec::new();
                 }
                 BracketClose => {
-                    let loop_instructions = current_instructions;
-                    current_instructions = loop_stack
+                    let body = current;
+                    current = loop_stack
                         .pop()
-                        .unwrap_or_else(|| panic!("Unmatched closing bracket at {}", i));
-                    let optimized = Self::optimize(loop_instructions);
-                    current_instructions.push(optimized);
+                        .ok_or(BrainfuckError::UnmatchedClosingBracket { pos: i })?;
+                    current.push(Self::optimize(body)?);
                 }
                 Ignore => {}
             }
         }
         if !loop_stack.is_empty() {
-            panic!("Unmatched opening bracket");
+            return Err(BrainfuckError::UnmatchedOpeningBracket);
         }
-        current_instructions
+        Ok(current)
+    }
+}
+
+/// Parses `--eof=zero|ones|unchanged`, defaulting to `LeaveUnchanged` when
+/// absent or unrecognized.
+fn parse_eof_policy(args: &[String]) -> EofPolicy {
+    match args.iter().find_map(|a| a.strip_prefix("--eof=")) {
+        Some("zero") => EofPolicy::Zero,
+        Some("ones") => EofPolicy::AllOnes,
+        _ => EofPolicy::LeaveUnchanged,
+    }
+}
+
+/// Parses `--tape=growable` or `--tape=fixed:<n>`, defaulting to the
+/// classic fixed 30,000-cell tape when absent or unrecognized.
+fn parse_tape_mode(args: &[String]) -> TapeMode {
+    match args.iter().find_map(|a| a.strip_prefix("--tape=")) {
+        Some("growable") => TapeMode::Growable,
+        Some(spec) => match spec.strip_prefix("fixed:").and_then(|n| n.parse().ok()) {
+            Some(n) => TapeMode::Fixed(n),
+            None => TapeMode::Fixed(DEFAULT_TAPE_LEN),
+        },
+        None => TapeMode::Fixed(DEFAULT_TAPE_LEN),
     }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let filepath = env::args().nth(1).unwrap_or_else(|| "1.bf".to_string());
+    let args: Vec<String> = env::args().collect();
+    let filepath = args.get(1).cloned().unwrap_or_else(|| "1.bf".to_string());
     let filename = env::current_dir()?.join(filepath);
     let content = std::fs::read_to_string(filename)?;
 
+    let options = MemoryOptions {
+        tape: parse_tape_mode(&args),
+        eof_policy: parse_eof_policy(&args),
+    };
     let mut interpreter = Interpreter::new(content);
 
     let run_time = std::time::Instant::now();
-    interpreter.run();
+    match args.iter().find_map(|a| a.strip_prefix("--cell=")) {
+        Some("u16") => interpreter.run::<u16>(options)?,
+        Some("u32") => interpreter.run::<u32>(options)?,
+        _ => interpreter.run::<u8>(options)?,
+    }
     println!("Finished in {}ms", run_time.elapsed().as_millis());
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use brainfuck::Input as BfInput;
+    use brainfuck::Output as BfOutput;
+
+    struct NullIo;
+    impl BfInput for NullIo {
+        fn read_byte(&mut self) -> Option<u8> {
+            None
+        }
+    }
+    impl BfOutput for NullIo {
+        fn write_bytes(&mut self, _bytes: &[u8]) {}
+    }
+
+    #[test]
+    fn move_right_errors_at_tape_limit_instead_of_panicking() {
+        let (mut input, mut output) = (NullIo, NullIo);
+        let options = MemoryOptions {
+            tape: TapeMode::Fixed(4),
+            eof_policy: EofPolicy::LeaveUnchanged,
+        };
+        let mut memory = Memory::<u8>::with_options(&mut input, &mut output, options);
+        assert!(matches!(
+            memory.move_pointer_right(10),
+            Err(BrainfuckError::PointerOutOfBounds)
+        ));
+    }
+
+    #[test]
+    fn runs_negative_offset_copy_loop_across_cell_widths() {
+        // `[-<+>]` copies the current cell one slot to the left via a
+        // negative-offset `Scaled` loop; confirm it runs cleanly for every
+        // selectable cell width now that the VM is generic over `Cell`.
+        fn runs<T: Cell>(ops: &[Op]) -> bool {
+            let (mut input, mut output) = (NullIo, NullIo);
+            let mut memory = Memory::<T>::with_options(&mut input, &mut output, MemoryOptions::default());
+            run_bytecode(ops, &mut memory).is_ok()
+        }
+
+        let mut interpreter = Interpreter::new(">++++[-<+>]".into());
+        let ast = interpreter.parse().unwrap();
+        let mut ops = Vec::new();
+        compile(&ast, &mut ops);
+
+        assert!(runs::<u8>(&ops));
+        assert!(runs::<u16>(&ops));
+        assert!(runs::<u32>(&ops));
+    }
+}
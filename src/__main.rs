@@ -1,15 +1,58 @@
-use std::env;
+#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+mod bytecode;
+mod disasm;
+mod error;
 mod interpreter;
+mod io;
 mod memory;
 mod token;
 mod parser;
+
+#[cfg(feature = "std")]
 use crate::interpreter::Interpreter;
+#[cfg(feature = "std")]
+use crate::io::StdIo;
+
+#[cfg(feature = "std")]
 fn main() {
-    let filepath = env::args().nth(1).unwrap_or("2.bf".to_string());
+    use std::env;
+
+    let args = env::args().collect::<Vec<_>>();
+    let filepath = args.get(1).cloned().unwrap_or("2.bf".to_string());
+    let growable = args.iter().any(|arg| arg == "--growable");
+    let dump = args.iter().any(|arg| arg == "--dump");
     let filename = env::current_dir().unwrap().join(filepath);
     let source = std::fs::read_to_string(filename).unwrap();
-    let mut interpreter = Interpreter::new(source);
+    let mut interpreter = if growable {
+        Interpreter::with_tape(source, 30_000, true)
+    } else {
+        Interpreter::new(source)
+    };
+
+    if dump {
+        match interpreter.dump() {
+            Ok(listing) => print!("{listing}"),
+            Err(e) => {
+                eprintln!("error: {e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-    interpreter.run();
+    let mut stdin = StdIo;
+    let mut stdout = StdIo;
+    if let Err(e) = interpreter.run(&mut stdin, &mut stdout) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
 }
+
+// The evaluator core (Expr/Parser/Token/Memory) is no_std+alloc-ready, but
+// this CLI entry point needs stdin/stdout/the filesystem, so it has nothing
+// to do without the std feature.
+#[cfg(not(feature = "std"))]
+fn main() {}
@@ -0,0 +1,142 @@
+use alloc::vec::Vec;
+
+use crate::error::BfError;
+use crate::memory::Memory;
+use crate::parser::Expr;
+
+/// A flat, jump-resolved instruction. Produced by `lower` from the `Expr`
+/// optimization IR so the interpreter can drive execution with a single
+/// program counter instead of recursing into nested `Loop`/`JumpOut` trees.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    Increment(u32),
+    Decrement(u32),
+    MoveRight(u32),
+    MoveLeft(u32),
+    Input,
+    Output,
+    MakeZero,
+    Offset { o: i32, v: i32 },
+    Multiply { targets: Vec<(i32, i32)> },
+    JumpIfZero(usize),
+    JumpIfNonZero(usize),
+}
+
+/// Lowers the optimized `Expr` tree into a flat `Vec<Op>`, resolving every
+/// `Loop`/`JumpOut` into a pair of absolute jumps around its body.
+pub fn lower(exprs: Vec<Expr>) -> Vec<Op> {
+    let mut ops = Vec::new();
+    lower_into(&exprs, &mut ops);
+    ops
+}
+
+fn lower_into(exprs: &[Expr], ops: &mut Vec<Op>) {
+    for expr in exprs {
+        match expr {
+            Expr::IncrementCount(n) => ops.push(Op::Increment(*n)),
+            Expr::DecrementCount(n) => ops.push(Op::Decrement(*n)),
+            Expr::MoveRightCount(n) => ops.push(Op::MoveRight(*n)),
+            Expr::MoveLeftCount(n) => ops.push(Op::MoveLeft(*n)),
+            Expr::Input => ops.push(Op::Input),
+            Expr::Output => ops.push(Op::Output),
+            Expr::MakeZero => ops.push(Op::MakeZero),
+            Expr::OffsetOp { o, v } => ops.push(Op::Offset { o: *o, v: *v }),
+            Expr::MultiplyOp { targets } => ops.push(Op::Multiply {
+                targets: targets.clone(),
+            }),
+            Expr::Loop(body) => lower_loop(body, ops),
+            Expr::JumpOut(inner) => lower_loop(core::slice::from_ref(inner), ops),
+        }
+    }
+}
+
+fn lower_loop(body: &[Expr], ops: &mut Vec<Op>) {
+    let jz_idx = ops.len();
+    ops.push(Op::JumpIfZero(0));
+    lower_into(body, ops);
+    let jnz_idx = ops.len();
+    ops.push(Op::JumpIfNonZero(jz_idx));
+    ops[jz_idx] = Op::JumpIfZero(jnz_idx + 1);
+}
+
+/// Runs a flat `Op` stream against `memory`, driven by a single program
+/// counter rather than recursing through the `Expr` tree.
+pub fn run(ops: &[Op], memory: &mut Memory<'_>) -> Result<(), BfError> {
+    let mut pc = 0;
+    while pc < ops.len() {
+        match &ops[pc] {
+            Op::Increment(n) => memory.increment_cell(*n),
+            Op::Decrement(n) => memory.decrement_cell(*n),
+            Op::MoveRight(n) => memory.move_pointer_right(*n)?,
+            Op::MoveLeft(n) => memory.move_pointer_left(*n)?,
+            Op::Input => memory.input_cell()?,
+            Op::Output => memory.output_cell()?,
+            Op::MakeZero => memory.cells[memory.pointer] = 0,
+            Op::Offset { o, v } => {
+                let target = memory.offset_index(*o)?;
+                memory.cells[target] = memory.cells[target].wrapping_add(*v as u8);
+            }
+            Op::Multiply { targets } => {
+                let current_value = memory.val();
+                if current_value != 0 {
+                    for (o, d) in targets {
+                        let target = memory.offset_index(*o)?;
+                        memory.cells[target] =
+                            memory.cells[target].wrapping_add((*d as u8).wrapping_mul(current_value));
+                    }
+                    memory.cells[memory.pointer] = 0;
+                }
+            }
+            Op::JumpIfZero(target) => {
+                if memory.val() == 0 {
+                    pc = *target;
+                    continue;
+                }
+            }
+            Op::JumpIfNonZero(target) => {
+                if memory.val() != 0 {
+                    pc = *target;
+                    continue;
+                }
+            }
+        }
+        pc += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{Input, Output};
+
+    struct NullIo;
+    impl Input for NullIo {
+        fn read_byte(&mut self) -> Result<Option<u8>, BfError> {
+            Ok(None)
+        }
+    }
+    impl Output for NullIo {
+        fn write_bytes(&mut self, _bytes: &[u8]) -> Result<(), BfError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn multiply_with_negative_target_past_origin_errors_instead_of_wrapping() {
+        // Regression test: `Op::Multiply`/`Op::Offset` used to resolve
+        // negative offsets via `pointer.wrapping_add(o as usize)`, which
+        // wraps to `usize::MAX` instead of reporting an out-of-bounds
+        // pointer when `|o| > pointer`.
+        let (mut input, mut output) = (NullIo, NullIo);
+        let mut memory = Memory::with_capacity(30, false, &mut input, &mut output);
+        let ops = vec![Op::Multiply {
+            targets: vec![(-1, 1)],
+        }];
+        memory.cells[memory.pointer] = 1;
+        assert!(matches!(
+            run(&ops, &mut memory),
+            Err(BfError::PointerOutOfBounds)
+        ));
+    }
+}
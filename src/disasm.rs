@@ -0,0 +1,54 @@
+use alloc::format;
+use alloc::string::String;
+
+use crate::parser::Expr;
+
+/// Renders optimized IR as a compact, stable text listing instead of Rust
+/// `Debug` output, so users can see which optimizations fired (`zero`,
+/// `scan`, `addat`, `mul`) without knowing `Expr`'s internal shape.
+/// Driven by the CLI's `--dump` flag.
+pub fn disasm(exprs: &[Expr]) -> String {
+    let mut out = String::new();
+    disasm_into(exprs, 0, &mut out);
+    out
+}
+
+fn disasm_into(exprs: &[Expr], depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    for expr in exprs {
+        match expr {
+            Expr::IncrementCount(n) => out.push_str(&format!("{indent}add +{n}\n")),
+            Expr::DecrementCount(n) => out.push_str(&format!("{indent}add -{n}\n")),
+            Expr::MoveRightCount(n) => out.push_str(&format!("{indent}move >{n}\n")),
+            Expr::MoveLeftCount(n) => out.push_str(&format!("{indent}move <{n}\n")),
+            Expr::Input => out.push_str(&format!("{indent}in\n")),
+            Expr::Output => out.push_str(&format!("{indent}out\n")),
+            Expr::MakeZero => out.push_str(&format!("{indent}zero\n")),
+            Expr::JumpOut(inner) => match inner.as_ref() {
+                Expr::MoveLeftCount(n) => out.push_str(&format!("{indent}scan <{n}\n")),
+                Expr::MoveRightCount(n) => out.push_str(&format!("{indent}scan >{n}\n")),
+                _ => unreachable!(),
+            },
+            Expr::OffsetOp { o, v } => {
+                out.push_str(&format!("{indent}addat {}\n", disasm_target(*o, *v)));
+            }
+            Expr::MultiplyOp { targets } => {
+                let rendered: alloc::vec::Vec<String> =
+                    targets.iter().map(|(o, v)| disasm_target(*o, *v)).collect();
+                out.push_str(&format!("{indent}mul {{ {} }}\n", rendered.join(", ")));
+            }
+            Expr::Loop(body) => {
+                out.push_str(&format!("{indent}loop {{\n"));
+                disasm_into(body, depth + 1, out);
+                out.push_str(&format!("{indent}}}\n"));
+            }
+        }
+    }
+}
+
+/// Renders a single `(offset, delta)` pair as `@offset *delta`, both with an
+/// explicit sign (e.g. `@-1 *+3`), shared by
+/// `OffsetOp` and `MultiplyOp`'s per-target text.
+fn disasm_target(o: i32, v: i32) -> String {
+    format!("@{o:+} *{v:+}")
+}
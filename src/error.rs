@@ -0,0 +1,55 @@
+use core::fmt;
+
+#[derive(Debug)]
+pub enum BfError {
+    UnmatchedClose { pos: usize },
+    UnmatchedOpen,
+    InfiniteIoLoop,
+    PointerUnderflow,
+    TapeLimitExceeded,
+    PointerOutOfBounds,
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+}
+
+impl fmt::Display for BfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BfError::UnmatchedClose { pos } => {
+                write!(f, "unmatched closing bracket at position {pos}")
+            }
+            BfError::UnmatchedOpen => write!(f, "unmatched opening bracket"),
+            BfError::InfiniteIoLoop => {
+                write!(f, "infinite loop: loop body is empty or only does input/output")
+            }
+            BfError::PointerUnderflow => {
+                write!(f, "pointer moved left past the start of the tape")
+            }
+            BfError::TapeLimitExceeded => {
+                write!(f, "pointer moved right past the end of a non-growable tape")
+            }
+            BfError::PointerOutOfBounds => {
+                write!(f, "offset target fell before the start of the tape")
+            }
+            #[cfg(feature = "std")]
+            BfError::Io(e) => write!(f, "I/O error: {e}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BfError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for BfError {
+    fn from(e: std::io::Error) -> Self {
+        BfError::Io(e)
+    }
+}
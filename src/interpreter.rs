@@ -1,9 +1,13 @@
-use std::env::args;
-use std::fs::File;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
 use std::io::Write;
 
-use crate::memory::Memory;
-use crate::parser::{Expr, Parser};
+use crate::bytecode;
+use crate::error::BfError;
+use crate::io::{Input, Output};
+use crate::memory::{Memory, DEFAULT_TAPE_LEN};
+use crate::parser::Parser;
 
 pub struct Interpreter {
     parser: Parser,
@@ -12,38 +16,64 @@ pub struct Interpreter {
 
 impl Interpreter {
     pub fn new(source: String) -> Self {
+        Self::with_tape(source, DEFAULT_TAPE_LEN, false)
+    }
+
+    pub fn with_tape(source: String, initial_tape_len: usize, growable: bool) -> Self {
         Self {
             parser: Parser::new(source),
-            executor: Executor,
+            executor: Executor {
+                initial_tape_len,
+                growable,
+            },
         }
     }
 
-    pub fn run(&mut self) {
-        let exprs = self.parser.parse();
+    /// Parses and optimizes the source, returning a human-readable listing
+    /// of the resulting `Expr` tree instead of executing it.
+    pub fn dump(&mut self) -> Result<String, BfError> {
+        let exprs = self.parser.parse()?;
+        Ok(crate::disasm::disasm(&exprs))
+    }
+
+    pub fn run(&mut self, input: &mut dyn Input, output: &mut dyn Output) -> Result<(), BfError> {
+        let exprs = self.parser.parse()?;
 
         // 如果是开发模式，则输出信息到文件
-        let a = args().collect::<Vec<_>>();
-        if a.iter().any(|arg| arg == "dev") {
-            let filename = (a[1].split(".").next().unwrap()).to_string() + ".txt";
-            let mut file = File::create(filename).unwrap();
-            writeln!(file, "{exprs:#?}\n").expect("Failed to write AST debug info");
+        #[cfg(feature = "std")]
+        {
+            let a = std::env::args().collect::<Vec<_>>();
+            if a.iter().any(|arg| arg == "dev") {
+                let filename = (a[1].split(".").next().unwrap()).to_string() + ".txt";
+                let mut file = std::fs::File::create(filename).unwrap();
+                writeln!(file, "{exprs:#?}\n").expect("Failed to write AST debug info");
+            }
         }
 
-        self.executor.execute(exprs);
+        let ops = bytecode::lower(exprs);
+        self.executor.execute(ops, input, output)
     }
 }
 
-struct Executor;
+struct Executor {
+    initial_tape_len: usize,
+    growable: bool,
+}
 
 impl Executor {
-    fn execute(&mut self, exprs: Vec<Expr>) {
-        let mut memory = Memory::new();
+    fn execute(
+        &mut self,
+        ops: Vec<bytecode::Op>,
+        input: &mut dyn Input,
+        output: &mut dyn Output,
+    ) -> Result<(), BfError> {
+        let mut memory = Memory::with_capacity(self.initial_tape_len, self.growable, input, output);
+        #[cfg(feature = "std")]
         let time = std::time::Instant::now();
-        exprs.into_iter().for_each(|e| {
-            e.effect(&mut memory);
-        });
-        let end = time.elapsed();
-        memory.flush();
-        println!("time :{}ms", end.as_millis());
+        bytecode::run(&ops, &mut memory)?;
+        memory.flush()?;
+        #[cfg(feature = "std")]
+        println!("time :{}ms", time.elapsed().as_millis());
+        Ok(())
     }
 }
@@ -0,0 +1,39 @@
+use crate::error::BfError;
+
+/// A pluggable byte source. Lets the interpreter be fed a fixed input buffer
+/// (tests, embedding) instead of always reading from stdin.
+pub trait Input {
+    fn read_byte(&mut self) -> Result<Option<u8>, BfError>;
+}
+
+/// A pluggable byte sink. Lets the interpreter capture output into a buffer
+/// (tests, embedding) instead of always writing to stdout.
+pub trait Output {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), BfError>;
+}
+
+#[cfg(feature = "std")]
+pub struct StdIo;
+
+#[cfg(feature = "std")]
+impl Input for StdIo {
+    fn read_byte(&mut self) -> Result<Option<u8>, BfError> {
+        use std::io::Read;
+        let mut buf = [0u8; 1];
+        match std::io::stdin().read(&mut buf) {
+            Ok(0) => Ok(None),
+            Ok(_) => Ok(Some(buf[0])),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Output for StdIo {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), BfError> {
+        use std::io::Write;
+        std::io::stdout().write_all(bytes)?;
+        std::io::stdout().flush()?;
+        Ok(())
+    }
+}
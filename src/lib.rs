@@ -1,51 +1,292 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
 use std::io::{Read, Write};
 
-pub struct Memory {
-    pub cells: Vec<u8>,
+/// Byte source the interpreter reads `,` cells from. The `std` feature wires
+/// this to stdin via [`StdIo`]; embedders (a fixed buffer, a host binding, a
+/// test harness) implement it directly so the core never touches `std::io`.
+pub trait Input {
+    fn read_byte(&mut self) -> Option<u8>;
+}
+
+/// Byte sink the interpreter writes `.` cells to.
+pub trait Output {
+    fn write_bytes(&mut self, bytes: &[u8]);
+}
+
+/// `Input`/`Output` backed by the process's stdin/stdout.
+#[cfg(feature = "std")]
+pub struct StdIo;
+
+#[cfg(feature = "std")]
+impl Input for StdIo {
+    fn read_byte(&mut self) -> Option<u8> {
+        let mut buf = [0u8; 1];
+        std::io::stdin().read_exact(&mut buf).ok()?;
+        Some(buf[0])
+    }
+}
+
+#[cfg(feature = "std")]
+impl Output for StdIo {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        let mut stdout = std::io::stdout();
+        stdout.write_all(bytes).unwrap();
+        stdout.flush().unwrap();
+    }
+}
+
+/// A tape cell width, chosen by the `Interpreter<T>`/`Memory<T>` type
+/// parameter (`MemoryOptions` has no cell-width field): classic 8-bit
+/// wrapping cells, or wider 16-/32-bit cells for programs that want more
+/// headroom before they wrap.
+pub trait Cell: Copy + PartialEq + 'static {
+    const ZERO: Self;
+    const MAX: Self;
+    fn wrapping_add(self, delta: i64) -> Self;
+    fn from_byte(b: u8) -> Self;
+    fn to_byte(self) -> u8;
+    fn to_u32(self) -> u32;
+}
+
+macro_rules! impl_cell {
+    ($($t:ty),*) => {
+        $(
+            impl Cell for $t {
+                const ZERO: Self = 0;
+                const MAX: Self = <$t>::MAX;
+                #[inline(always)]
+                fn wrapping_add(self, delta: i64) -> Self {
+                    (self as i64).wrapping_add(delta) as Self
+                }
+                #[inline(always)]
+                fn from_byte(b: u8) -> Self {
+                    b as Self
+                }
+                #[inline(always)]
+                fn to_byte(self) -> u8 {
+                    self as u8
+                }
+                #[inline(always)]
+                fn to_u32(self) -> u32 {
+                    self as u32
+                }
+            }
+        )*
+    };
+}
+
+impl_cell!(u8, u16, u32);
+
+/// What a `,` does to the current cell once the input source is exhausted.
+/// Real brainfuck dialects disagree on this, so it's a runtime choice
+/// rather than a hardcoded one.
+#[derive(Debug, Clone, Copy)]
+pub enum EofPolicy {
+    LeaveUnchanged,
+    Zero,
+    AllOnes,
+}
+
+/// Whether the tape is a fixed-size array with bounds checks, or grows to
+/// fit whatever the program touches.
+#[derive(Debug, Clone, Copy)]
+pub enum TapeMode {
+    Fixed(usize),
+    Growable,
+}
+
+/// The classic 30,000-cell tape size most brainfuck interpreters use (e.g.
+/// brainfrsck's `MEMORY_SIZE`).
+pub const DEFAULT_TAPE_LEN: usize = 30_000;
+
+/// Tape shape and I/O edge-case behavior, threaded through
+/// `Interpreter::new`/`with_options` and `Memory::new`.
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryOptions {
+    pub tape: TapeMode,
+    pub eof_policy: EofPolicy,
+}
+
+impl Default for MemoryOptions {
+    fn default() -> Self {
+        Self {
+            tape: TapeMode::Fixed(DEFAULT_TAPE_LEN),
+            eof_policy: EofPolicy::LeaveUnchanged,
+        }
+    }
+}
+
+/// Failure modes that used to `panic!`/`process::exit` the whole process.
+/// Returned instead so a library embedder gets a recoverable error rather
+/// than a killed process.
+#[derive(Debug)]
+pub enum BrainfuckError {
+    UnmatchedClosingBracket { pos: usize },
+    UnmatchedOpeningBracket,
+    EmptyLoop,
+    InfiniteIoLoop,
+    PointerOutOfBounds,
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+}
+
+impl fmt::Display for BrainfuckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BrainfuckError::UnmatchedClosingBracket { pos } => {
+                write!(f, "unmatched closing bracket at {pos}")
+            }
+            BrainfuckError::UnmatchedOpeningBracket => write!(f, "unmatched opening bracket"),
+            BrainfuckError::EmptyLoop => write!(f, "empty loop detected"),
+            BrainfuckError::InfiniteIoLoop => write!(f, "infinite loop of IO operations detected"),
+            BrainfuckError::PointerOutOfBounds => write!(f, "pointer moved out of bounds"),
+            #[cfg(feature = "std")]
+            BrainfuckError::Io(e) => write!(f, "I/O error: {e}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BrainfuckError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BrainfuckError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for BrainfuckError {
+    fn from(e: std::io::Error) -> Self {
+        BrainfuckError::Io(e)
+    }
+}
+
+/// The tape plus the I/O handles `,`/`.` read and write through. Handles are
+/// borrowed for the run rather than owned, so the same `Input`/`Output` can
+/// be reused (or a test harness can inspect an in-memory buffer) after.
+pub struct Memory<'io, T: Cell> {
+    pub cells: Vec<T>,
     pub pointer: usize,
     pub output_buffer: Vec<u8>,
+    tape: TapeMode,
+    eof_policy: EofPolicy,
+    input: &'io mut dyn Input,
+    output: &'io mut dyn Output,
 }
 
-impl Memory {
-    pub fn new() -> Self {
+impl<'io, T: Cell> Memory<'io, T> {
+    pub fn new(input: &'io mut dyn Input, output: &'io mut dyn Output) -> Self {
+        Self::with_options(input, output, MemoryOptions::default())
+    }
+
+    pub fn with_options(
+        input: &'io mut dyn Input,
+        output: &'io mut dyn Output,
+        options: MemoryOptions,
+    ) -> Self {
+        let initial = match options.tape {
+            TapeMode::Fixed(n) => n,
+            TapeMode::Growable => DEFAULT_TAPE_LEN,
+        };
         Self {
-            cells: vec![0; 30000],
+            cells: vec![T::ZERO; initial],
             pointer: 0,
             output_buffer: Vec::with_capacity(64),
+            tape: options.tape,
+            eof_policy: options.eof_policy,
+            input,
+            output,
         }
     }
 
     #[inline(always)]
-    pub fn val(&self) -> u8 {
+    pub fn val(&self) -> T {
         self.cells[self.pointer]
     }
 
     #[inline(always)]
     pub fn increment_cell(&mut self, c: u32) {
-        self.cells[self.pointer] = self.cells[self.pointer].wrapping_add(c as u8);
+        self.cells[self.pointer] = self.cells[self.pointer].wrapping_add(c as i64);
     }
 
     #[inline(always)]
     pub fn decrement_cell(&mut self, c: u32) {
-        self.cells[self.pointer] = self.cells[self.pointer].wrapping_sub(c as u8);
+        self.cells[self.pointer] = self.cells[self.pointer].wrapping_add(-(c as i64));
+    }
+
+    /// Ensures `target` is a valid index: grows the tape if it's
+    /// `Growable`, or reports `PointerOutOfBounds` if it's `Fixed`.
+    fn ensure_in_bounds(&mut self, target: usize) -> Result<(), BrainfuckError> {
+        if target >= self.cells.len() {
+            match self.tape {
+                TapeMode::Fixed(_) => return Err(BrainfuckError::PointerOutOfBounds),
+                TapeMode::Growable => self.cells.resize(target + 1, T::ZERO),
+            }
+        }
+        Ok(())
     }
 
     #[inline(always)]
-    pub fn move_pointer_right(&mut self, c: u32) {
-        self.pointer += c as usize;
+    pub fn move_pointer_right(&mut self, c: u32) -> Result<(), BrainfuckError> {
+        let target = self.pointer + c as usize;
+        self.ensure_in_bounds(target)?;
+        self.pointer = target;
+        Ok(())
     }
 
     #[inline(always)]
-    pub fn move_pointer_left(&mut self, c: u32) {
-        if self.pointer < 1 {
-            panic!("Pointer underflow: attempted to move left at index 0");
+    pub fn move_pointer_left(&mut self, c: u32) -> Result<(), BrainfuckError> {
+        let c = c as usize;
+        if c > self.pointer {
+            return Err(BrainfuckError::PointerOutOfBounds);
         }
-        self.pointer -= c as usize;
+        self.pointer -= c;
+        Ok(())
+    }
+
+    /// Resolves the absolute index `pointer + o` for an `OffsetOp`-style
+    /// access, honoring the same bounds mode as `move_pointer_*`.
+    fn offset_index(&mut self, o: i32) -> Result<usize, BrainfuckError> {
+        let idx = self.pointer as i64 + o as i64;
+        if idx < 0 {
+            return Err(BrainfuckError::PointerOutOfBounds);
+        }
+        let idx = idx as usize;
+        self.ensure_in_bounds(idx)?;
+        Ok(idx)
+    }
+
+    #[inline(always)]
+    pub fn add_at(&mut self, o: i32, v: i32) -> Result<(), BrainfuckError> {
+        let idx = self.offset_index(o)?;
+        self.cells[idx] = self.cells[idx].wrapping_add(v as i64);
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn offset_make_zero(&mut self, o: i32, v: i32) -> Result<(), BrainfuckError> {
+        let current = self.val();
+        if current != T::ZERO {
+            let idx = self.offset_index(o)?;
+            self.cells[self.pointer] = T::ZERO;
+            self.cells[idx] = self.cells[idx].wrapping_add(v as i64 * current.to_u32() as i64);
+        }
+        Ok(())
     }
 
     #[inline(always)]
     pub fn output_cell(&mut self) {
-        self.output_buffer.push(self.cells[self.pointer]);
+        self.output_buffer.push(self.cells[self.pointer].to_byte());
         if self.output_buffer.len() >= 64 {
             self.flush();
         }
@@ -54,17 +295,21 @@ impl Memory {
     #[inline(always)]
     pub fn flush(&mut self) {
         if !self.output_buffer.is_empty() {
-            std::io::stdout().write_all(&self.output_buffer).unwrap();
-            std::io::stdout().flush().unwrap();
+            self.output.write_bytes(&self.output_buffer);
             self.output_buffer.clear();
         }
     }
 
     #[inline(always)]
     pub fn input_cell(&mut self) {
-        let mut buf = [0u8; 1];
-        std::io::stdin().read_exact(&mut buf).unwrap();
-        self.cells[self.pointer] = buf[0];
+        match self.input.read_byte() {
+            Some(byte) => self.cells[self.pointer] = T::from_byte(byte),
+            None => match self.eof_policy {
+                EofPolicy::LeaveUnchanged => {}
+                EofPolicy::Zero => self.cells[self.pointer] = T::ZERO,
+                EofPolicy::AllOnes => self.cells[self.pointer] = T::MAX,
+            },
+        }
     }
 }
 
@@ -114,95 +359,87 @@ pub enum Expr {
 
 impl Expr {
     #[inline(always)]
-    pub fn run_effect(&self, memory: &mut Memory) {
+    pub fn run_effect<T: Cell>(&self, memory: &mut Memory<'_, T>) -> Result<(), BrainfuckError> {
         match self {
             Expr::IncrementCount(count) => memory.increment_cell(*count),
             Expr::DecrementCount(count) => memory.decrement_cell(*count),
-            Expr::MoveRightCount(count) => memory.move_pointer_right(*count),
-            Expr::MoveLeftCount(count) => memory.move_pointer_left(*count),
+            Expr::MoveRightCount(count) => memory.move_pointer_right(*count)?,
+            Expr::MoveLeftCount(count) => memory.move_pointer_left(*count)?,
             Expr::Output => memory.output_cell(),
             Expr::Input => memory.input_cell(),
             Expr::Loop { exprs, one_time } => match *one_time {
                 false => {
-                    while memory.val() != 0 {
-                        exprs.iter().for_each(|e| e.run_effect(memory));
+                    while memory.val() != T::ZERO {
+                        for e in exprs {
+                            e.run_effect(memory)?;
+                        }
                     }
                 }
-                true if memory.val() != 0 => {
-                    let times = memory.val() as u32;
-                    exprs.iter().for_each(|e| match e {
-                        Expr::IncrementCount(count) => memory.increment_cell(*count * times),
-                        Expr::DecrementCount(count) => memory.decrement_cell(*count * times),
-                        Expr::MoveLeftCount(n) => memory.move_pointer_left(*n),
-                        Expr::MoveRightCount(n) => memory.move_pointer_right(*n),
-                        _ => unreachable!(),
-                    });
+                true if memory.val() != T::ZERO => {
+                    let times = memory.val().to_u32();
+                    for e in exprs {
+                        match e {
+                            Expr::IncrementCount(count) => memory.increment_cell(*count * times),
+                            Expr::DecrementCount(count) => memory.decrement_cell(*count * times),
+                            Expr::MoveLeftCount(n) => memory.move_pointer_left(*n)?,
+                            Expr::MoveRightCount(n) => memory.move_pointer_right(*n)?,
+                            _ => unreachable!(),
+                        }
+                    }
                 }
-                true => return,
+                true => {}
             },
             Expr::MakeZero => {
-                memory.cells[memory.pointer] = 0;
+                memory.cells[memory.pointer] = T::ZERO;
             }
             Expr::JumpOut(expr) => {
-                while memory.val() != 0 {
+                while memory.val() != T::ZERO {
                     match expr.as_ref() {
-                        Expr::MoveLeftCount(n) => {
-                            memory.move_pointer_left(*n);
-                        }
-                        Expr::MoveRightCount(n) => {
-                            memory.move_pointer_right(*n);
-                        }
-                        _ => {
-                            unreachable!()
-                        }
+                        Expr::MoveLeftCount(n) => memory.move_pointer_left(*n)?,
+                        Expr::MoveRightCount(n) => memory.move_pointer_right(*n)?,
+                        _ => unreachable!(),
                     }
                 }
             }
-            Expr::OffsetOp { o, v } => {
-                memory.cells[memory.pointer.wrapping_add(*o as usize)] += *v as u8;
-            }
-            Expr::OffsetMakeZeroOp { o, v } => {
-                let current_value = memory.val();
-                if current_value != 0 {
-                    memory.cells[memory.pointer] = 0;
-                    memory.cells[memory.pointer.wrapping_add(*o as usize)] +=
-                        (*v as u8).wrapping_mul(current_value);
-                }
-            }
+            Expr::OffsetOp { o, v } => memory.add_at(*o, *v)?,
+            Expr::OffsetMakeZeroOp { o, v } => memory.offset_make_zero(*o, *v)?,
         }
+        Ok(())
     }
 }
 
-pub struct Interpreter {
+pub struct Interpreter<T: Cell> {
     source: String,
     pub exprs: Vec<Expr>,
+    options: MemoryOptions,
+    _cell: core::marker::PhantomData<T>,
 }
 
-impl Interpreter {
+impl<T: Cell> Interpreter<T> {
     pub fn new(source: String) -> Self {
+        Self::with_options(source, MemoryOptions::default())
+    }
+
+    pub fn with_options(source: String, options: MemoryOptions) -> Self {
         Self {
             source,
             exprs: Vec::new(),
+            options,
+            _cell: core::marker::PhantomData,
         }
     }
 
     #[inline(always)]
-    fn optimize(mut exprs: Vec<Expr>) -> Expr {
-        loop {
+    fn optimize(mut exprs: Vec<Expr>) -> Result<Expr, BrainfuckError> {
+        let expr = loop {
             match exprs.len() {
-                0 | 1 => {
+                0 => return Err(BrainfuckError::EmptyLoop),
+                1 => {
                     break match <[Expr; 1]>::try_from(exprs) {
                         Ok([Expr::DecrementCount(_)] | [Expr::IncrementCount(_)]) => Expr::MakeZero,
                         Ok([e @ Expr::MoveLeftCount(_)]) => Expr::JumpOut(e.into()),
                         Ok([e @ Expr::MoveRightCount(_)]) => Expr::JumpOut(e.into()),
-                        Err(err) => {
-                            eprintln!("Infinite loop :{:#?}", err);
-                            std::process::exit(1);
-                        }
-                        _ => {
-                            eprintln!("Infinite loop of IO operations detected");
-                            std::process::exit(1);
-                        }
+                        _ => return Err(BrainfuckError::InfiniteIoLoop),
                     };
                 }
                 2 => {
@@ -238,7 +475,7 @@ impl Interpreter {
                         }
                     }
                     if !jump_out && offset == 0 {
-                        return Expr::Loop {
+                        break Expr::Loop {
                             exprs,
                             one_time: true,
                         };
@@ -298,10 +535,11 @@ impl Interpreter {
                     }
                 }
             }
-        }
+        };
+        Ok(expr)
     }
 
-    pub fn parse(&mut self) {
+    pub fn parse(&mut self) -> Result<(), BrainfuckError> {
         let mut loop_stack: Vec<Vec<Expr>> = Vec::new();
         let mut current_exprs: Vec<Expr> = Vec::new();
 
@@ -333,34 +571,45 @@ impl Interpreter {
                     let loop_exprs = current_exprs;
                     current_exprs = loop_stack
                         .pop()
-                        .unwrap_or_else(|| panic!("Unmatched closing bracket at {}", i));
-                    let exps = Self::optimize(loop_exprs);
+                        .ok_or(BrainfuckError::UnmatchedClosingBracket { pos: i })?;
+                    let exps = Self::optimize(loop_exprs)?;
                     current_exprs.push(exps);
                 }
                 Token::Ignore => {}
             }
         }
         if !loop_stack.is_empty() {
-            panic!("Unmatched opening bracket");
+            return Err(BrainfuckError::UnmatchedOpeningBracket);
         }
         self.exprs = current_exprs;
+        Ok(())
     }
 
-    pub fn run(&mut self) {
+    pub fn run(
+        &mut self,
+        input: &mut dyn Input,
+        output: &mut dyn Output,
+    ) -> Result<(), BrainfuckError> {
+        #[cfg(feature = "std")]
         let time = std::time::Instant::now();
-        self.parse();
+        self.parse()?;
+        #[cfg(feature = "std")]
         println!("Parsed in {}ms", time.elapsed().as_millis());
-        let args = std::env::args().collect::<Vec<String>>();
-        if args.iter().any(|s| s == "dev") {
-            let filename = (args[1].split(".").next().unwrap()).to_string() + ".txt";
-            let mut file = std::fs::File::create(filename).unwrap();
-            writeln!(file, "{:#?}", self.exprs).unwrap();
+        #[cfg(feature = "std")]
+        {
+            let args = std::env::args().collect::<Vec<String>>();
+            if args.iter().any(|s| s == "dev") {
+                let filename = (args[1].split(".").next().unwrap()).to_string() + ".txt";
+                let mut file = std::fs::File::create(filename)?;
+                writeln!(file, "{:#?}", self.exprs)?;
+            }
         }
 
-        let mut memory = Memory::new();
-        self.exprs
-            .iter()
-            .for_each(|expr| expr.run_effect(&mut memory));
+        let mut memory = Memory::<T>::with_options(input, output, self.options);
+        for expr in &self.exprs {
+            expr.run_effect(&mut memory)?;
+        }
         memory.flush();
+        Ok(())
     }
 }
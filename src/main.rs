@@ -1,3 +1,8 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+extern crate alloc;
+
+use alloc::{boxed::Box, collections::BTreeMap, format, string::String, vec, vec::Vec};
+#[cfg(feature = "std")]
 use std::{
   env,
   fs::File,
@@ -5,6 +10,79 @@ use std::{
   path::Path,
 };
 
+/// Byte source the interpreter reads `,` cells from. The `std` feature wires
+/// this to stdin; embedders (a fixed input buffer, a WASM host, a test
+/// harness) implement it directly so the core never touches `std::io`.
+trait Input {
+  fn read_byte(&mut self) -> Option<u8>;
+}
+
+/// Byte sink the interpreter writes `.` cells to.
+trait Output {
+  fn write_bytes(&mut self, bytes: &[u8]);
+}
+
+/// What a `,` does to the current cell once the input source is exhausted.
+/// Real Brainfuck programs rely on different EOF conventions, so this is
+/// a runtime choice rather than a hardcoded one. The bundled CLI only ever
+/// selects `LeaveUnchanged`; the other variants are here for embedders
+/// calling `Interpreter::with_io` directly.
+#[derive(Debug, Clone, Copy)]
+#[allow(dead_code)]
+enum EofPolicy {
+  LeaveUnchanged,
+  Zero,
+  SetTo(u8),
+}
+
+/// Failure modes that used to `panic!`/`process::exit` the whole process.
+/// Returned instead so an embedder gets a recoverable error.
+#[derive(Debug)]
+enum InterpreterError {
+  UnmatchedOpen,
+  UnmatchedClose { pos: usize },
+  InfiniteLoop,
+  UnexpectedEof,
+  TapeLimitExceeded,
+}
+
+impl core::fmt::Display for InterpreterError {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    match self {
+      InterpreterError::UnmatchedOpen => write!(f, "unmatched opening bracket"),
+      InterpreterError::UnmatchedClose { pos } => {
+        write!(f, "unmatched closing bracket at {pos}")
+      }
+      InterpreterError::InfiniteLoop => write!(f, "infinite loop detected"),
+      InterpreterError::UnexpectedEof => write!(f, "unexpected end of input"),
+      InterpreterError::TapeLimitExceeded => write!(f, "tape grew past its configured limit"),
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InterpreterError {}
+
+#[cfg(feature = "std")]
+struct StdIo;
+
+#[cfg(feature = "std")]
+impl Input for StdIo {
+  fn read_byte(&mut self) -> Option<u8> {
+    let mut buf = [0u8; 1];
+    io::stdin().read_exact(&mut buf).ok()?;
+    Some(buf[0])
+  }
+}
+
+#[cfg(feature = "std")]
+impl Output for StdIo {
+  fn write_bytes(&mut self, bytes: &[u8]) {
+    io::stdout().write_all(bytes).unwrap();
+    io::stdout().flush().unwrap();
+  }
+}
+
 #[derive(Debug)]
 enum Token {
   Plus,
@@ -37,18 +115,18 @@ impl Token {
 
 pub trait Uint:
   Copy
-  + std::fmt::Debug
-  + std::ops::Add<Output = Self>
-  + std::ops::AddAssign<Self>
-  + std::ops::Sub<Output = Self>
-  + std::ops::SubAssign<Self>
-  + std::ops::Mul<Output = Self>
-  + std::ops::MulAssign<Self>
-  + std::ops::Div<Output = Self>
-  + std::ops::DivAssign<Self>
-  + std::ops::Rem<Output = Self>
-  + std::cmp::Ord
-  + std::cmp::Eq
+  + core::fmt::Debug
+  + core::ops::Add<Output = Self>
+  + core::ops::AddAssign<Self>
+  + core::ops::Sub<Output = Self>
+  + core::ops::SubAssign<Self>
+  + core::ops::Mul<Output = Self>
+  + core::ops::MulAssign<Self>
+  + core::ops::Div<Output = Self>
+  + core::ops::DivAssign<Self>
+  + core::ops::Rem<Output = Self>
+  + core::cmp::Ord
+  + core::cmp::Eq
 {
   const ZERO: Self;
   const ONE: Self;
@@ -100,21 +178,55 @@ macro_rules! impl_unsigned_int {
 
 impl_unsigned_int!(u8, u16, u32, u64);
 
-struct Memory<T> {
+/// Default tape size, matching the classic 30,000-cell convention.
+const DEFAULT_TAPE_LEN: usize = 30_000;
+/// Minimum chunk prepended when the tape grows leftwards; rightward growth
+/// doubles the tape length instead.
+const DEFAULT_GROW_CHUNK: usize = 32 * 1024;
+
+struct Memory<T, I, O>
+where
+  I: Input,
+  O: Output,
+{
   cells:         Vec<T>,
   pointer:       usize,
   output_buffer: Vec<T>,
+  input:         I,
+  output:        O,
+  eof_policy:    EofPolicy,
+  max_cells:     Option<usize>,
+  grow_chunk:    usize,
 }
 
-impl<T> Memory<T>
+impl<T, I, O> Memory<T, I, O>
 where
   T: Uint,
+  I: Input,
+  O: Output,
 {
-  fn new() -> Self {
+  fn new(input: I, output: O, eof_policy: EofPolicy) -> Self {
+    Self::with_capacity(DEFAULT_TAPE_LEN, None, input, output, eof_policy)
+  }
+
+  /// `max_cells` bounds how far the tape may grow in either direction;
+  /// exceeding it returns `TapeLimitExceeded` instead of growing forever.
+  fn with_capacity(
+    initial: usize,
+    max_cells: Option<usize>,
+    input: I,
+    output: O,
+    eof_policy: EofPolicy,
+  ) -> Self {
     Self {
-      cells:         vec![T::ZERO; 30000],
-      pointer:       0,
+      cells: vec![T::ZERO; initial.max(1)],
+      pointer: 0,
       output_buffer: Vec::with_capacity(128),
+      input,
+      output,
+      eof_policy,
+      max_cells,
+      grow_chunk: DEFAULT_GROW_CHUNK,
     }
   }
   #[inline(always)]
@@ -129,13 +241,74 @@ where
   fn decrement_cell(&mut self, c: T) {
     unsafe { *self.cells.get_unchecked_mut(self.pointer) -= c }
   }
+  /// Grows the tape rightwards (amortized doubling) so `min_len` is a
+  /// valid length, or errors if that would exceed `max_cells`.
+  fn grow_right(&mut self, min_len: usize) -> Result<(), InterpreterError> {
+    if let Some(max) = self.max_cells {
+      if min_len > max {
+        return Err(InterpreterError::TapeLimitExceeded);
+      }
+    }
+    let mut new_len = self.cells.len().max(1);
+    while new_len < min_len {
+      new_len = new_len.saturating_mul(2);
+    }
+    if let Some(max) = self.max_cells {
+      new_len = new_len.min(max);
+    }
+    self.cells.resize(new_len, T::ZERO);
+    Ok(())
+  }
+  /// Grows the tape leftwards by prepending a zero-filled chunk and
+  /// shifting `pointer` to keep pointing at the same logical cell.
+  fn grow_left(&mut self, needed: usize) -> Result<(), InterpreterError> {
+    if let Some(max) = self.max_cells {
+      if self.cells.len() + needed > max {
+        return Err(InterpreterError::TapeLimitExceeded);
+      }
+    }
+    let mut chunk = needed.max(self.grow_chunk);
+    if let Some(max) = self.max_cells {
+      chunk = chunk.min(max - self.cells.len());
+    }
+    let mut grown = Vec::with_capacity(self.cells.len() + chunk);
+    grown.resize(chunk, T::ZERO);
+    grown.extend_from_slice(&self.cells);
+    self.cells = grown;
+    self.pointer += chunk;
+    Ok(())
+  }
   #[inline(always)]
-  fn move_pointer_left(&mut self, c: usize) {
-    unsafe { self.pointer = self.pointer.unchecked_sub(c) }
+  fn move_pointer_left(&mut self, c: usize) -> Result<(), InterpreterError> {
+    if c > self.pointer {
+      self.grow_left(c - self.pointer)?;
+    }
+    self.pointer -= c;
+    Ok(())
   }
   #[inline(always)]
-  fn move_pointer_right(&mut self, c: usize) {
-    unsafe { self.pointer = self.pointer.unchecked_add(c) }
+  fn move_pointer_right(&mut self, c: usize) -> Result<(), InterpreterError> {
+    let target = self.pointer + c;
+    if target >= self.cells.len() {
+      self.grow_right(target + 1)?;
+    }
+    self.pointer = target;
+    Ok(())
+  }
+  /// Resolves `pointer - o`, growing the tape leftwards if needed.
+  fn offset_left_index(&mut self, o: usize) -> Result<usize, InterpreterError> {
+    if o > self.pointer {
+      self.grow_left(o - self.pointer)?;
+    }
+    Ok(self.pointer - o)
+  }
+  /// Resolves `pointer + o`, growing the tape rightwards if needed.
+  fn offset_right_index(&mut self, o: usize) -> Result<usize, InterpreterError> {
+    let idx = self.pointer + o;
+    if idx >= self.cells.len() {
+      self.grow_right(idx + 1)?;
+    }
+    Ok(idx)
   }
   #[inline(always)]
   fn output_cell(&mut self) {
@@ -151,7 +324,7 @@ where
         .output_buffer
         .iter()
         .flat_map(|&codepoint| {
-          std::char::from_u32(codepoint.as_u32())
+          core::char::from_u32(codepoint.as_u32())
             .expect("Invalid UTF-8 codepoint")
             .encode_utf8(&mut [0; 4])
             .bytes()
@@ -159,32 +332,40 @@ where
         })
         .collect();
 
-      std::io::stdout().write_all(&utf8_bytes).unwrap();
-      std::io::stdout().flush().unwrap();
+      self.output.write_bytes(&utf8_bytes);
       self.output_buffer.clear();
     }
   }
-  fn input_cell(&mut self) -> io::Result<()> {
+  /// A clean EOF on the very first byte is handled by `eof_policy`; a
+  /// partial/invalid UTF-8 sequence cut short by EOF is a real error.
+  fn input_cell(&mut self) -> Result<(), InterpreterError> {
     let mut buf = [0u8; 4];
-    io::stdin().read_exact(&mut buf[..1])?;
-    let _ = 1u32.wrapping_add(10);
+    let Some(first) = self.input.read_byte() else {
+      match self.eof_policy {
+        EofPolicy::LeaveUnchanged => {}
+        EofPolicy::Zero => self.cells[self.pointer] = T::ZERO,
+        EofPolicy::SetTo(v) => self.cells[self.pointer] = T::from_u64(v as u64),
+      }
+      return Ok(());
+    };
+    buf[0] = first;
     let len = match buf[0] {
       0..=0x7F => 1,    // ASCII
       0xC2..=0xDF => 2, // 2 byte
       0xE0..=0xEF => 3, // 3 byte
       0xF0..=0xF7 => 4, // 4 byte
-      _ => {
-        return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid UTF-8"));
-      }
+      _ => return Err(InterpreterError::UnexpectedEof),
     };
-    if len > 1 {
-      io::stdin().read_exact(&mut buf[1..len])?;
+    for b in buf.iter_mut().take(len).skip(1) {
+      *b = self
+        .input
+        .read_byte()
+        .ok_or(InterpreterError::UnexpectedEof)?;
     }
-    let c = std::str::from_utf8(&buf[..len])
-      .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid UTF-8"))?
-      .chars()
-      .next()
-      .ok_or(io::ErrorKind::InvalidData)?;
+    let c = core::str::from_utf8(&buf[..len])
+      .ok()
+      .and_then(|s| s.chars().next())
+      .ok_or(InterpreterError::UnexpectedEof)?;
     self.cells[self.pointer] = T::from_u64(c as u64);
     Ok(())
   }
@@ -211,9 +392,139 @@ where
   JumpOut(Box<Expr<T>>),
   OffsetOp(Box<Expr<T>>, Box<Expr<T>>),
   OffsetMakeZeroOp(Box<Expr<T>>, Box<Expr<T>>),
+  /// General multiply/copy loop: `cells[pointer]` is zeroed and each
+  /// `(offset, value)` target receives `value * cells[pointer]` added at
+  /// `pointer + offset`, one `Mul`/`Add` per target instead of N loop
+  /// iterations. Built by `try_multiply_loop` from a body that didn't fit
+  /// the single-target `OffsetMakeZeroOp` shape.
+  MultiplyLoop(Vec<(Expr<T>, Expr<T>)>),
 }
 use Expr::*;
 
+/// Detects a general multiply/copy loop in an already offset-folded loop
+/// body: every op must be an `OffsetOp`, or a bare `IncrementCount`/
+/// `DecrementCount`/`MoveLeftCount`/`MoveRightCount` touching the current
+/// cell, the net pointer movement across the body must be zero, and the
+/// current cell's net delta must be exactly `-1`. Any I/O, nested loop, or
+/// other shape bails out so the caller falls back to a normal loop.
+fn try_multiply_loop<T: Uint>(exprs: &[Expr<T>]) -> Option<Vec<(Expr<T>, Expr<T>)>> {
+  let mut offset: i64 = 0;
+  let mut deltas: BTreeMap<i64, i64> = BTreeMap::new();
+  for e in exprs {
+    match e {
+      MoveLeftCount(n) => offset -= (*n).as_u32() as i64,
+      MoveRightCount(n) => offset += (*n).as_u32() as i64,
+      IncrementCount(n) => *deltas.entry(offset).or_insert(0) += (*n).as_u32() as i64,
+      DecrementCount(n) => *deltas.entry(offset).or_insert(0) -= (*n).as_u32() as i64,
+      OffsetOp(o, v) => {
+        let target = offset
+          + match o.as_ref() {
+            MoveLeftCount(n) => -((*n).as_u32() as i64),
+            MoveRightCount(n) => (*n).as_u32() as i64,
+            _ => return None,
+          };
+        let delta = match v.as_ref() {
+          IncrementCount(n) => (*n).as_u32() as i64,
+          DecrementCount(n) => -((*n).as_u32() as i64),
+          _ => return None,
+        };
+        *deltas.entry(target).or_insert(0) += delta;
+      }
+      _ => return None,
+    }
+  }
+  if offset != 0 {
+    return None;
+  }
+  if deltas.remove(&0) != Some(-1) {
+    return None;
+  }
+  let targets: Vec<(Expr<T>, Expr<T>)> = deltas
+    .into_iter()
+    .filter(|(_, delta)| *delta != 0)
+    .map(|(off, delta)| {
+      let off_expr = if off < 0 {
+        MoveLeftCount(T::from_u64(off.unsigned_abs()))
+      } else {
+        MoveRightCount(T::from_u64(off as u64))
+      };
+      let val_expr = if delta > 0 {
+        IncrementCount(T::from_u64(delta as u64))
+      } else {
+        DecrementCount(T::from_u64(delta.unsigned_abs()))
+      };
+      (off_expr, val_expr)
+    })
+    .collect();
+  if targets.is_empty() {
+    None
+  } else {
+    Some(targets)
+  }
+}
+
+/// Renders optimized IR as a compact, stable text listing instead of Rust
+/// `Debug` output, so users can see which optimizations fired (`zero`,
+/// `scan`, `addat`, `mul`, scaled `loop*`/`loop1`) without knowing `Expr`'s
+/// internal shape. Driven by the `--emit=ir` CLI flag.
+fn disasm<T: Uint>(exprs: &[Expr<T>]) -> String {
+  let mut out = String::new();
+  disasm_into(exprs, 0, &mut out);
+  out
+}
+
+fn disasm_into<T: Uint>(exprs: &[Expr<T>], depth: usize, out: &mut String) {
+  let indent = "  ".repeat(depth);
+  for e in exprs {
+    match e {
+      IncrementCount(n) => out.push_str(&format!("{indent}add +{}\n", n.as_u32())),
+      DecrementCount(n) => out.push_str(&format!("{indent}add -{}\n", n.as_u32())),
+      MoveRightCount(n) => out.push_str(&format!("{indent}move >{}\n", n.as_u32())),
+      MoveLeftCount(n) => out.push_str(&format!("{indent}move <{}\n", n.as_u32())),
+      Input => out.push_str(&format!("{indent}in\n")),
+      Output => out.push_str(&format!("{indent}out\n")),
+      MakeZero => out.push_str(&format!("{indent}zero\n")),
+      JumpOut(expr) => match expr.as_ref() {
+        MoveLeftCount(n) => out.push_str(&format!("{indent}scan <{}\n", n.as_u32())),
+        MoveRightCount(n) => out.push_str(&format!("{indent}scan >{}\n", n.as_u32())),
+        _ => unreachable!(),
+      },
+      OffsetOp(o, v) => out.push_str(&format!("{indent}addat {}\n", disasm_target(o, v))),
+      OffsetMakeZeroOp(o, v) => out.push_str(&format!("{indent}mul {}\n", disasm_target(o, v))),
+      MultiplyLoop(targets) => {
+        let rendered: Vec<String> = targets.iter().map(|(o, v)| disasm_target(o, v)).collect();
+        out.push_str(&format!("{indent}mul {{ {} }}\n", rendered.join(", ")));
+      }
+      Loop { exprs, loty } => {
+        let tag = match loty {
+          LoopType::Loop => "loop",
+          LoopType::Mul => "loop*",
+          LoopType::Once => "loop1",
+        };
+        out.push_str(&format!("{indent}{tag} {{\n"));
+        disasm_into(exprs, depth + 1, out);
+        out.push_str(&format!("{indent}}}\n"));
+      }
+    }
+  }
+}
+
+/// Renders a single `(move, op)` pair as `@+offset *value`, shared by
+/// `OffsetOp`, `OffsetMakeZeroOp` and `MultiplyLoop`'s per-target text.
+fn disasm_target<T: Uint>(o: &Expr<T>, v: &Expr<T>) -> String {
+  let offset = match o {
+    MoveLeftCount(n) => format!("@-{}", n.as_u32()),
+    MoveRightCount(n) => format!("@+{}", n.as_u32()),
+    _ => unreachable!(),
+  };
+  let value = match v {
+    IncrementCount(n) => format!("*{}", n.as_u32()),
+    DecrementCount(n) => format!("*-{}", n.as_u32()),
+    _ => unreachable!(),
+  };
+  format!("{offset} {value}")
+}
+
 #[derive(Debug)]
 enum LoopType {
   Once,
@@ -221,36 +532,287 @@ enum LoopType {
   Loop,
 }
 
-struct Interpreter<T>
+/// Flat bytecode form of `Expr`. Every fused tree node becomes a single
+/// opcode and `Loop { loty: LoopType::Loop, .. }` lowers to a
+/// `JumpIfZero`/`JumpIfNonZero` pair with absolute, pre-resolved targets,
+/// so `run_bytecode` never recurses.
+#[derive(Debug)]
+enum Op<T>
+where
+  T: Uint,
+{
+  IncrementCount(T),
+  DecrementCount(T),
+  MoveRightCount(T),
+  MoveLeftCount(T),
+  Input,
+  Output,
+  MakeZero,
+  JumpOutLeft(T),
+  JumpOutRight(T),
+  OffsetIncLeft(T, T),
+  OffsetIncRight(T, T),
+  OffsetDecLeft(T, T),
+  OffsetDecRight(T, T),
+  OffsetZeroIncLeft(T, T),
+  OffsetZeroIncRight(T, T),
+  OffsetZeroDecLeft(T, T),
+  OffsetZeroDecRight(T, T),
+  // Resolved form of `Expr::MultiplyLoop`: `(offset_is_left, offset,
+  // value_is_increment, value)` per target.
+  Multiply(Vec<(bool, T, bool, T)>),
+  // `LoopType::Mul`/`LoopType::Once` bodies only ever contain increments,
+  // decrements and moves (enforced by `optimize`), so they collapse to a
+  // single scaled, non-looping opcode instead of a jump pair.
+  ScaledLoop { body: Vec<Op<T>>, mul: bool },
+  JumpIfZero(usize),
+  JumpIfNonZero(usize),
+}
+
+/// Lowers an optimized `Expr` tree into `ops`, appending opcodes in order.
+/// `Loop` nodes push a `JumpIfZero` placeholder, recurse into the body,
+/// then emit the closing `JumpIfNonZero` and back-patch the placeholder's
+/// target to the instruction right after it.
+fn compile<T: Uint>(exprs: &[Expr<T>], ops: &mut Vec<Op<T>>) {
+  for e in exprs {
+    match e {
+      IncrementCount(n) => ops.push(Op::IncrementCount(*n)),
+      DecrementCount(n) => ops.push(Op::DecrementCount(*n)),
+      MoveRightCount(n) => ops.push(Op::MoveRightCount(*n)),
+      MoveLeftCount(n) => ops.push(Op::MoveLeftCount(*n)),
+      Output => ops.push(Op::Output),
+      Input => ops.push(Op::Input),
+      MakeZero => ops.push(Op::MakeZero),
+      JumpOut(expr) => match expr.as_ref() {
+        MoveLeftCount(n) => ops.push(Op::JumpOutLeft(*n)),
+        MoveRightCount(n) => ops.push(Op::JumpOutRight(*n)),
+        _ => unreachable!(),
+      },
+      OffsetOp(o, v) => match (o.as_ref(), v.as_ref()) {
+        (MoveLeftCount(o), IncrementCount(v)) => ops.push(Op::OffsetIncLeft(*o, *v)),
+        (MoveRightCount(o), IncrementCount(v)) => ops.push(Op::OffsetIncRight(*o, *v)),
+        (MoveLeftCount(o), DecrementCount(v)) => ops.push(Op::OffsetDecLeft(*o, *v)),
+        (MoveRightCount(o), DecrementCount(v)) => ops.push(Op::OffsetDecRight(*o, *v)),
+        _ => unreachable!(),
+      },
+      OffsetMakeZeroOp(o, v) => match (o.as_ref(), v.as_ref()) {
+        (MoveLeftCount(o), IncrementCount(v)) => ops.push(Op::OffsetZeroIncLeft(*o, *v)),
+        (MoveRightCount(o), IncrementCount(v)) => ops.push(Op::OffsetZeroIncRight(*o, *v)),
+        (MoveLeftCount(o), DecrementCount(v)) => ops.push(Op::OffsetZeroDecLeft(*o, *v)),
+        (MoveRightCount(o), DecrementCount(v)) => ops.push(Op::OffsetZeroDecRight(*o, *v)),
+        _ => unreachable!(),
+      },
+      MultiplyLoop(targets) => {
+        let resolved = targets
+          .iter()
+          .map(|(o, v)| match (o, v) {
+            (MoveLeftCount(o), IncrementCount(v)) => (true, *o, true, *v),
+            (MoveRightCount(o), IncrementCount(v)) => (false, *o, true, *v),
+            (MoveLeftCount(o), DecrementCount(v)) => (true, *o, false, *v),
+            (MoveRightCount(o), DecrementCount(v)) => (false, *o, false, *v),
+            _ => unreachable!(),
+          })
+          .collect();
+        ops.push(Op::Multiply(resolved));
+      }
+      Loop {
+        exprs,
+        loty: LoopType::Loop,
+      } => {
+        let jump_if_zero = ops.len();
+        ops.push(Op::JumpIfZero(0)); // patched below
+        let start = ops.len();
+        compile(exprs, ops);
+        ops.push(Op::JumpIfNonZero(start));
+        let end = ops.len();
+        ops[jump_if_zero] = Op::JumpIfZero(end);
+      }
+      Loop { exprs, loty } => {
+        let mut body = Vec::with_capacity(exprs.len());
+        compile(exprs, &mut body);
+        ops.push(Op::ScaledLoop {
+          body,
+          mul: matches!(loty, LoopType::Mul),
+        });
+      }
+    }
+  }
+}
+
+/// Executes a flat `Op` program with a single `pc`-driven loop, the way a
+/// bytecode VM dispatches over a resolved instruction stream.
+fn run_bytecode<T, I, O>(ops: &[Op<T>], memory: &mut Memory<T, I, O>) -> Result<(), InterpreterError>
+where
+  T: Uint,
+  I: Input,
+  O: Output,
+{
+  let mut pc = 0;
+  while pc < ops.len() {
+    match &ops[pc] {
+      Op::IncrementCount(n) => memory.increment_cell(*n),
+      Op::DecrementCount(n) => memory.decrement_cell(*n),
+      Op::MoveRightCount(n) => memory.move_pointer_right((*n).as_usize())?,
+      Op::MoveLeftCount(n) => memory.move_pointer_left((*n).as_usize())?,
+      Op::Output => memory.output_cell(),
+      Op::Input => memory.input_cell()?,
+      Op::MakeZero => memory.cells[memory.pointer] = T::ZERO,
+      Op::JumpOutLeft(n) => {
+        while memory.cells[memory.pointer] != T::ZERO {
+          memory.move_pointer_left((*n).as_usize())?;
+        }
+      }
+      Op::JumpOutRight(n) => {
+        while memory.cells[memory.pointer] != T::ZERO {
+          memory.move_pointer_right((*n).as_usize())?;
+        }
+      }
+      Op::OffsetIncLeft(o, v) => {
+        let idx = memory.offset_left_index((*o).as_usize())?;
+        memory.cells[idx] = memory.cells[idx].wrapping_add(*v);
+      }
+      Op::OffsetIncRight(o, v) => {
+        let idx = memory.offset_right_index((*o).as_usize())?;
+        memory.cells[idx] = memory.cells[idx].wrapping_add(*v);
+      }
+      Op::OffsetDecLeft(o, v) => {
+        let idx = memory.offset_left_index((*o).as_usize())?;
+        memory.cells[idx] = memory.cells[idx].wrapping_sub(*v);
+      }
+      Op::OffsetDecRight(o, v) => {
+        let idx = memory.offset_right_index((*o).as_usize())?;
+        memory.cells[idx] = memory.cells[idx].wrapping_sub(*v);
+      }
+      Op::OffsetZeroIncLeft(o, v) => {
+        let val = memory.val();
+        if val != T::ZERO {
+          memory.cells[memory.pointer] = T::ZERO;
+          let idx = memory.offset_left_index((*o).as_usize())?;
+          memory.cells[idx] = memory.cells[idx].wrapping_add(*v * val);
+        }
+      }
+      Op::OffsetZeroIncRight(o, v) => {
+        let val = memory.val();
+        if val != T::ZERO {
+          memory.cells[memory.pointer] = T::ZERO;
+          let idx = memory.offset_right_index((*o).as_usize())?;
+          memory.cells[idx] = memory.cells[idx].wrapping_add(*v * val);
+        }
+      }
+      Op::OffsetZeroDecLeft(o, v) => {
+        let val = memory.val();
+        if val != T::ZERO {
+          memory.cells[memory.pointer] = T::ZERO;
+          let idx = memory.offset_left_index((*o).as_usize())?;
+          memory.cells[idx] = memory.cells[idx].wrapping_sub(*v * val);
+        }
+      }
+      Op::OffsetZeroDecRight(o, v) => {
+        let val = memory.val();
+        if val != T::ZERO {
+          memory.cells[memory.pointer] = T::ZERO;
+          let idx = memory.offset_right_index((*o).as_usize())?;
+          memory.cells[idx] = memory.cells[idx].wrapping_sub(*v * val);
+        }
+      }
+      Op::Multiply(targets) => {
+        let n = memory.val();
+        if n != T::ZERO {
+          memory.cells[memory.pointer] = T::ZERO;
+          for (is_left, o, is_inc, v) in targets {
+            let idx = if *is_left {
+              memory.offset_left_index((*o).as_usize())?
+            } else {
+              memory.offset_right_index((*o).as_usize())?
+            };
+            memory.cells[idx] = if *is_inc {
+              memory.cells[idx].wrapping_add(v.wrapping_mul(n))
+            } else {
+              memory.cells[idx].wrapping_sub(v.wrapping_mul(n))
+            };
+          }
+        }
+      }
+      Op::ScaledLoop { body, mul } => {
+        let multiple = memory.val();
+        if multiple != T::ZERO {
+          for e in body {
+            match e {
+              Op::IncrementCount(n) => {
+                memory.increment_cell(if *mul { multiple } else { *n * multiple })
+              }
+              Op::DecrementCount(n) => {
+                memory.decrement_cell(if *mul { multiple } else { *n * multiple })
+              }
+              Op::MoveLeftCount(n) => memory.move_pointer_left((*n).as_usize())?,
+              Op::MoveRightCount(n) => memory.move_pointer_right((*n).as_usize())?,
+              _ => unreachable!(),
+            }
+          }
+        }
+      }
+      Op::JumpIfZero(target) => {
+        if memory.val() == T::ZERO {
+          pc = *target;
+          continue;
+        }
+      }
+      Op::JumpIfNonZero(target) => {
+        if memory.val() != T::ZERO {
+          pc = *target;
+          continue;
+        }
+      }
+    }
+    pc += 1;
+  }
+  Ok(())
+}
+
+struct Interpreter<T, I, O>
 where
   T: Uint,
+  I: Input,
+  O: Output,
 {
   source: String,
-  memory: Memory<T>,
+  memory: Memory<T, I, O>,
   ast:    Vec<Expr<T>>,
 }
 
-impl<T> Interpreter<T>
+#[cfg(feature = "std")]
+impl<T> Interpreter<T, StdIo, StdIo>
 where
   T: Uint,
 {
   fn new(source: String) -> Self {
+    Self::with_io(source, StdIo, StdIo, EofPolicy::LeaveUnchanged)
+  }
+}
+
+impl<T, I, O> Interpreter<T, I, O>
+where
+  T: Uint,
+  I: Input,
+  O: Output,
+{
+  fn with_io(source: String, input: I, output: O, eof_policy: EofPolicy) -> Self {
     Self {
       source,
-      memory: Memory::<T>::new(),
+      memory: Memory::new(input, output, eof_policy),
       ast: vec![],
     }
   }
   #[inline(always)]
-  fn optimize(mut exprs: Vec<Expr<T>>) -> Expr<T> {
+  fn optimize(mut exprs: Vec<Expr<T>>) -> Result<Expr<T>, InterpreterError> {
     if exprs.len() >= 6 {
-      let mut offset = T::ZERO;
+      let mut offset: i64 = 0;
       let mut jump_out = false;
       let mut mul = true;
       for e in exprs.iter() {
         match e {
-          MoveLeftCount(n) => offset -= *n,
-          MoveRightCount(n) => offset += *n,
+          MoveLeftCount(n) => offset -= (*n).as_u32() as i64,
+          MoveRightCount(n) => offset += (*n).as_u32() as i64,
           IncrementCount(n) | DecrementCount(n) => {
             if *n != T::ONE {
               mul = false
@@ -262,28 +824,22 @@ where
           }
         }
       }
-      if !jump_out && offset == T::ZERO {
-        return Loop {
+      if !jump_out && offset == 0 {
+        return Ok(Loop {
           exprs,
           loty: if mul { LoopType::Mul } else { LoopType::Once },
-        };
+        });
       }
     }
-    loop {
+    let expr = loop {
       match exprs.len() {
-        0 => {
-          eprintln!("Infinite loop :{:#?}", exprs);
-          std::process::exit(1);
-        }
+        0 => return Err(InterpreterError::InfiniteLoop),
         1 => {
           break match <[Expr<T>; 1]>::try_from(exprs) {
             Ok([DecrementCount(_)] | [IncrementCount(_)]) => MakeZero,
             Ok([e @ MoveLeftCount(_)]) => JumpOut(e.into()),
             Ok([e @ MoveRightCount(_)]) => JumpOut(e.into()),
-            _ => {
-              eprintln!("Infinite loop of IO operations detected");
-              std::process::exit(1);
-            }
+            _ => return Err(InterpreterError::InfiniteLoop),
           };
         }
         2 => {
@@ -328,6 +884,8 @@ where
           }
           if matched {
             continue;
+          } else if let Some(targets) = try_multiply_loop(&exprs) {
+            break MultiplyLoop(targets);
           } else {
             break Loop {
               exprs,
@@ -336,10 +894,11 @@ where
           }
         }
       }
-    }
+    };
+    Ok(expr)
   }
 
-  fn parse(&mut self) {
+  fn parse(&mut self) -> Result<(), InterpreterError> {
     let mut loop_stack: Vec<Vec<Expr<T>>> = Vec::new();
     let mut current_exprs: Vec<Expr<T>> = Vec::new();
 
@@ -371,157 +930,103 @@ where
           let loop_exprs = current_exprs;
           current_exprs = loop_stack
             .pop()
-            .unwrap_or_else(|| panic!("Unmatched closing bracket at {}", i));
+            .ok_or(InterpreterError::UnmatchedClose { pos: i })?;
 
-          let exps = Self::optimize(loop_exprs);
+          let exps = Self::optimize(loop_exprs)?;
           current_exprs.push(exps);
         }
         Ignore => {}
       }
     }
     if !loop_stack.is_empty() {
-      panic!("Unmatched opening bracket");
+      return Err(InterpreterError::UnmatchedOpen);
     }
     self.ast = current_exprs;
+    Ok(())
   }
-  fn run(&mut self) {
+  /// Convenience driver for the `std` CLI: parses, optionally dumps the
+  /// `dev` IR or `--emit=ir` disassembly, then compiles and executes.
+  /// Embedders without `std` call `parse`/`compile`/`run_bytecode` directly
+  /// instead.
+  #[cfg(feature = "std")]
+  fn run(&mut self) -> Result<(), InterpreterError> {
     let time = std::time::Instant::now();
-    self.parse();
+    self.parse()?;
     println!("Parsed in {}ms", time.elapsed().as_millis());
 
     let time = std::time::Instant::now();
-    if Some("dev") == std::env::args().nth(2).as_deref() {
-      let filename = std::env::args().nth(1).unwrap();
-      let filename = Path::new(&filename).file_stem().unwrap();
-      let mut file = File::create(format!("{}.txt", filename.to_str().unwrap())).unwrap();
-      writeln!(file, "{:#?}", self.ast).unwrap();
+    match std::env::args().nth(2).as_deref() {
+      Some("dev") => {
+        let filename = std::env::args().nth(1).unwrap();
+        let filename = Path::new(&filename).file_stem().unwrap();
+        let mut file = File::create(format!("{}.txt", filename.to_str().unwrap())).unwrap();
+        writeln!(file, "{:#?}", self.ast).unwrap();
+      }
+      Some("--emit=ir") => print!("{}", disasm(&self.ast)),
+      _ => {}
     }
     println!("Wrote in {}ms", time.elapsed().as_millis());
 
-    #[inline(always)]
-    fn execute<T>(exprs: &[Expr<T>], memory: &mut Memory<T>)
-    where
-      T: Uint,
-    {
-      for e in exprs {
-        match e {
-          IncrementCount(count) => memory.increment_cell(*count),
-          DecrementCount(count) => memory.decrement_cell(*count),
-          MoveRightCount(count) => memory.move_pointer_right((*count).as_usize()),
-          MoveLeftCount(count) => memory.move_pointer_left((*count).as_usize()),
-          Output => memory.output_cell(),
-          Input => memory.input_cell().unwrap(),
-          Loop { exprs, loty } => match loty {
-            LoopType::Mul => {
-              let multiple = memory.val();
-              for e in exprs {
-                match e {
-                  IncrementCount(_) => memory.increment_cell(multiple),
-                  DecrementCount(_) => memory.decrement_cell(multiple),
-                  MoveLeftCount(n) => memory.move_pointer_left((*n).as_usize()),
-                  MoveRightCount(n) => memory.move_pointer_right((*n).as_usize()),
-                  _ => unreachable!(),
-                }
-              }
-            }
-            LoopType::Once => {
-              let multiple = memory.val();
-              for e in exprs {
-                match e {
-                  IncrementCount(count) => memory.increment_cell(*count * multiple),
-                  DecrementCount(count) => memory.decrement_cell(*count * multiple),
-                  MoveLeftCount(n) => memory.move_pointer_left((*n).as_usize()),
-                  MoveRightCount(n) => memory.move_pointer_right((*n).as_usize()),
-                  _ => unreachable!(),
-                }
-              }
-            }
-            LoopType::Loop => {
-              while memory.val() != T::ZERO {
-                execute(exprs, memory);
-              }
-            }
-          },
-          MakeZero => {
-            memory.cells[memory.pointer] = T::ZERO;
-          }
-          JumpOut(expr) => {
-            while memory.cells[memory.pointer] != T::ZERO {
-              match expr.as_ref() {
-                MoveLeftCount(n) => {
-                  memory.move_pointer_left((*n).as_usize());
-                }
-                MoveRightCount(n) => {
-                  memory.move_pointer_right((*n).as_usize());
-                }
-                _ => {
-                  unreachable!()
-                }
-              }
-            }
-          }
-          OffsetOp(o, v) => match (o.as_ref(), v.as_ref()) {
-            (MoveLeftCount(o), IncrementCount(v)) => {
-              let idx = memory.pointer.wrapping_sub((*o).as_usize());
-              memory.cells[idx] = memory.cells[idx].wrapping_add(*v);
-            }
-            (MoveRightCount(o), IncrementCount(v)) => {
-              let idx = memory.pointer.wrapping_add((*o).as_usize());
-              memory.cells[idx] = memory.cells[idx].wrapping_add(*v);
-            }
-            (MoveLeftCount(o), DecrementCount(v)) => {
-              let idx = memory.pointer.wrapping_sub((*o).as_usize());
-              memory.cells[idx] = memory.cells[idx].wrapping_sub(*v);
-            }
-            (MoveRightCount(o), DecrementCount(v)) => {
-              let idx = memory.pointer.wrapping_add((*o).as_usize());
-              memory.cells[idx] = memory.cells[idx].wrapping_sub(*v);
-            }
-            _ => unreachable!(),
-          },
-          OffsetMakeZeroOp(left, right) => {
-            let val: T = memory.cells[memory.pointer];
-            if val != T::ZERO {
-              memory.cells[memory.pointer] = T::ZERO;
-
-              match (left.as_ref(), right.as_ref()) {
-                (MoveLeftCount(o), IncrementCount(v)) => {
-                  let idx = memory.pointer.wrapping_sub((*o).as_usize());
-                  memory.cells[idx] = memory.cells[idx].wrapping_add(*v * val);
-                }
-                (MoveRightCount(o), IncrementCount(v)) => {
-                  let idx = memory.pointer.wrapping_add((*o).as_usize());
-                  memory.cells[idx] = memory.cells[idx].wrapping_add(*v * val);
-                }
-                (MoveLeftCount(o), DecrementCount(v)) => {
-                  let idx = memory.pointer.wrapping_sub((*o).as_usize());
-                  memory.cells[idx] = memory.cells[idx].wrapping_sub(*v * val);
-                }
-                (MoveRightCount(o), DecrementCount(v)) => {
-                  let idx = memory.pointer.wrapping_add((*o).as_usize());
-                  memory.cells[idx] = memory.cells[idx].wrapping_sub(*v * val);
-                }
-                _ => unreachable!(),
-              }
-            }
-          }
-        }
-      }
-    }
-    execute::<T>(&self.ast, &mut self.memory);
+    let mut ops = Vec::new();
+    compile(&self.ast, &mut ops);
+    run_bytecode(&ops, &mut self.memory)?;
     self.memory.flush();
+    Ok(())
   }
 }
 
+#[cfg(feature = "std")]
 fn main() -> Result<(), Box<dyn std::error::Error>> {
   let filepath = env::args().nth(1).unwrap();
   let fullpath = env::current_dir()?.join(filepath);
   let content = std::fs::read_to_string(fullpath)?;
-  let mut interpreter = Interpreter::<u8>::new(content);
+  let mut interpreter = Interpreter::<u8, StdIo, StdIo>::new(content);
 
   let time = std::time::Instant::now();
 
-  interpreter.run();
+  interpreter.run()?;
   println!("Finished in {}ms", time.elapsed().as_millis());
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  struct NullIo;
+  impl Input for NullIo {
+    fn read_byte(&mut self) -> Option<u8> {
+      None
+    }
+  }
+  impl Output for NullIo {
+    fn write_bytes(&mut self, _bytes: &[u8]) {}
+  }
+
+  #[test]
+  fn move_right_errors_at_tape_limit_instead_of_panicking() {
+    let mut memory: Memory<u8, _, _> =
+      Memory::with_capacity(4, Some(4), NullIo, NullIo, EofPolicy::LeaveUnchanged);
+    assert!(matches!(
+      memory.move_pointer_right(10),
+      Err(InterpreterError::TapeLimitExceeded)
+    ));
+  }
+
+  #[test]
+  fn runs_left_copy_loop_without_underflowing_offset() {
+    // Regression test: `Interpreter::optimize`'s Mul/Once fast path tracked
+    // `offset: T` (unsigned), underflowing on any 6+-op loop body that
+    // moves left before its compensating right move.
+    let mut interpreter = Interpreter::<u8, NullIo, NullIo>::with_io(
+      "+[<+<+>>-]".into(),
+      NullIo,
+      NullIo,
+      EofPolicy::LeaveUnchanged,
+    );
+    interpreter.parse().unwrap();
+    let mut ops = Vec::new();
+    compile(&interpreter.ast, &mut ops);
+    assert!(run_bytecode(&ops, &mut interpreter.memory).is_ok());
+  }
+}
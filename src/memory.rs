@@ -1,17 +1,34 @@
-use std::io::{Read, Write};
+use alloc::{vec, vec::Vec};
 
-pub struct Memory {
+use crate::error::BfError;
+use crate::io::{Input, Output};
+
+pub const DEFAULT_TAPE_LEN: usize = 30_000;
+const GROWTH_CHUNK: usize = 32 * 1024;
+
+pub struct Memory<'io> {
     pub cells: Vec<u8>,
     pub pointer: usize,
     pub output_buffer: Vec<u8>,
+    growable: bool,
+    input: &'io mut dyn Input,
+    output: &'io mut dyn Output,
 }
 
-impl Memory {
-    pub fn new() -> Self {
+impl<'io> Memory<'io> {
+    pub fn with_capacity(
+        initial: usize,
+        growable: bool,
+        input: &'io mut dyn Input,
+        output: &'io mut dyn Output,
+    ) -> Self {
         Self {
-            cells: vec![0; 30000],
+            cells: vec![0; initial],
             pointer: 0,
             output_buffer: Vec::with_capacity(64),
+            growable,
+            input,
+            output,
         }
     }
     #[inline(always)]
@@ -26,41 +43,107 @@ impl Memory {
     pub fn decrement_cell(&mut self, c: u32) {
         self.cells[self.pointer] = self.cells[self.pointer].wrapping_sub(c as u8);
     }
+    // Grows the tape by GROWTH_CHUNK-sized (or larger, if needed) increments
+    // when `index` would otherwise fall off the end, or reports
+    // `TapeLimitExceeded` instead of panicking when growth is disabled.
     #[inline(always)]
-    pub fn move_pointer_right(&mut self, c: u32) {
-        let new_pointer = self.pointer + c as usize;
-        if new_pointer >= self.cells.len() {
-            panic!("Pointer overflow: attempted to move right beyond memory bounds");
+    pub fn ensure_capacity(&mut self, index: usize) -> Result<(), BfError> {
+        if index < self.cells.len() {
+            return Ok(());
         }
+        if !self.growable {
+            return Err(BfError::TapeLimitExceeded);
+        }
+        let needed = index + 1 - self.cells.len();
+        let grow_by = needed.max(GROWTH_CHUNK);
+        self.cells.resize(self.cells.len() + grow_by, 0);
+        Ok(())
+    }
+    #[inline(always)]
+    pub fn move_pointer_right(&mut self, c: u32) -> Result<(), BfError> {
+        let new_pointer = self.pointer + c as usize;
+        self.ensure_capacity(new_pointer)?;
         self.pointer = new_pointer;
+        Ok(())
     }
+    // Resolves a signed offset from the current pointer to an absolute index,
+    // rejecting (rather than wrapping) offsets that would cross the tape
+    // origin, and growing/bounds-checking the far end via `ensure_capacity`.
     #[inline(always)]
-    pub fn move_pointer_left(&mut self, c: u32) {
+    pub fn offset_index(&mut self, o: i32) -> Result<usize, BfError> {
+        let idx = self.pointer as i64 + o as i64;
+        if idx < 0 {
+            return Err(BfError::PointerOutOfBounds);
+        }
+        let idx = idx as usize;
+        self.ensure_capacity(idx)?;
+        Ok(idx)
+    }
+    #[inline(always)]
+    pub fn move_pointer_left(&mut self, c: u32) -> Result<(), BfError> {
         let step = c as usize;
         if self.pointer < step {
-            panic!("Pointer underflow: attempted to move left beyond memory bounds");
+            return Err(BfError::PointerUnderflow);
         }
         self.pointer -= step;
+        Ok(())
     }
     #[inline(always)]
-    pub fn output_cell(&mut self) {
+    pub fn output_cell(&mut self) -> Result<(), BfError> {
         self.output_buffer.push(self.cells[self.pointer]);
         if self.output_buffer.len() >= 64 {
-            self.flush();
+            self.flush()?;
         }
+        Ok(())
     }
     #[inline(always)]
-    pub fn flush(&mut self) {
+    pub fn flush(&mut self) -> Result<(), BfError> {
         if !self.output_buffer.is_empty() {
-            std::io::stdout().write_all(&self.output_buffer).unwrap();
-            std::io::stdout().flush().unwrap();
+            self.output.write_bytes(&self.output_buffer)?;
             self.output_buffer.clear();
         }
+        Ok(())
     }
     #[inline(always)]
-    pub fn input_cell(&mut self) {
-        let mut buf = [0u8; 1];
-        std::io::stdin().read_exact(&mut buf).unwrap();
-        self.cells[self.pointer] = buf[0];
+    pub fn input_cell(&mut self) -> Result<(), BfError> {
+        if let Some(b) = self.input.read_byte()? {
+            self.cells[self.pointer] = b;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NullIo;
+    impl Input for NullIo {
+        fn read_byte(&mut self) -> Result<Option<u8>, BfError> {
+            Ok(None)
+        }
+    }
+    impl Output for NullIo {
+        fn write_bytes(&mut self, _bytes: &[u8]) -> Result<(), BfError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn move_right_grows_past_default_len_when_growable() {
+        let (mut input, mut output) = (NullIo, NullIo);
+        let mut memory = Memory::with_capacity(DEFAULT_TAPE_LEN, true, &mut input, &mut output);
+        assert!(memory.move_pointer_right(DEFAULT_TAPE_LEN as u32 + 10).is_ok());
+        assert!(memory.cells.len() > DEFAULT_TAPE_LEN);
+    }
+
+    #[test]
+    fn move_right_errors_instead_of_panicking_when_not_growable() {
+        let (mut input, mut output) = (NullIo, NullIo);
+        let mut memory = Memory::with_capacity(DEFAULT_TAPE_LEN, false, &mut input, &mut output);
+        assert!(matches!(
+            memory.move_pointer_right(DEFAULT_TAPE_LEN as u32),
+            Err(BfError::TapeLimitExceeded)
+        ));
     }
 }
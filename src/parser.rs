@@ -1,6 +1,6 @@
-use std::process::{self};
+use alloc::{boxed::Box, string::String, vec::Vec};
 
-use crate::{memory::Memory, token::Token};
+use crate::{error::BfError, token::Token};
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum Expr {
@@ -16,62 +16,14 @@ pub enum Expr {
     MakeZero,
     JumpOut(Box<Expr>),
     OffsetOp { o: i32, v: i32 },
-    OffsetMakeZeroOp { o: i32, v: i32 },
+    MultiplyOp { targets: Vec<(i32, i32)> },
 }
 
 use Expr::*;
 use Token::*;
 
 impl Expr {
-    #[inline(always)]
-    pub fn effect(&self, memory: &mut Memory) {
-        match self {
-            IncrementCount(n) => {
-                memory.increment_cell(*n);
-            }
-            DecrementCount(n) => {
-                memory.decrement_cell(*n);
-            }
-            MoveLeftCount(n) => {
-                memory.move_pointer_left(*n);
-            }
-            MoveRightCount(n) => {
-                memory.move_pointer_right(*n);
-            }
-            Loop(exprs) => {
-                while memory.val() != 0 {
-                    exprs.iter().for_each(|expr| expr.effect(memory));
-                }
-            }
-            Input => {
-                memory.input_cell();
-            }
-            Output => {
-                memory.output_cell();
-            }
-            MakeZero => {
-                memory.cells[memory.pointer] = 0;
-            }
-            JumpOut(expr) => {
-                while memory.val() != 0 {
-                    expr.effect(memory);
-                }
-            }
-            OffsetOp { o, v } => {
-                memory.cells[memory.pointer.wrapping_add(*o as usize)] += *v as u8;
-            }
-            OffsetMakeZeroOp { o, v } => {
-                let current_value = memory.val();
-                if current_value != 0 {
-                    memory.cells[memory.pointer] = 0;
-                    memory.cells[memory.pointer.wrapping_add(*o as usize)] +=
-                        (*v as u8).wrapping_mul(current_value);
-                }
-            }
-        }
-    }
-
-    pub fn from_tokens(tokens: Vec<Token>) -> Vec<Expr> {
+    pub fn from_tokens(tokens: Vec<Token>) -> Result<Vec<Expr>, BfError> {
         let mut loop_stack: Vec<Vec<Expr>> = Vec::new();
         let mut current_exprs: Vec<Expr> = Vec::new();
         for (i, c) in tokens.into_iter().enumerate() {
@@ -102,18 +54,18 @@ impl Expr {
                     let loop_exprs = current_exprs;
                     current_exprs = loop_stack
                         .pop()
-                        .unwrap_or_else(|| panic!("Unmatched closing bracket at {i}"));
+                        .ok_or(BfError::UnmatchedClose { pos: i })?;
 
-                    let expr = Parser::optimize(loop_exprs);
+                    let expr = Parser::optimize(loop_exprs)?;
                     current_exprs.push(expr);
                 }
                 Ignore => {}
             }
         }
         if !loop_stack.is_empty() {
-            panic!("Unmatched opening bracket");
+            return Err(BfError::UnmatchedOpen);
         }
-        current_exprs
+        Ok(current_exprs)
     }
 }
 
@@ -126,22 +78,19 @@ impl Parser {
         Self { source }
     }
 
-    fn single_loop_expr_optimize(exprs: Vec<Expr>) -> Expr {
+    fn single_loop_expr_optimize(exprs: Vec<Expr>) -> Result<Expr, BfError> {
         match exprs[..] {
-            [DecrementCount(_)] | [IncrementCount(_)] => MakeZero,
-            [MoveLeftCount(n)] => JumpOut(Box::new(MoveLeftCount(n))),
-            [MoveRightCount(n)] => JumpOut(Box::new(MoveRightCount(n))),
+            [DecrementCount(_)] | [IncrementCount(_)] => Ok(MakeZero),
+            [MoveLeftCount(n)] => Ok(JumpOut(Box::new(MoveLeftCount(n)))),
+            [MoveRightCount(n)] => Ok(JumpOut(Box::new(MoveRightCount(n)))),
             [..] if exprs.len() > 1 => Self::multiple_loop_expr_optimize(exprs),
-            _ => {
-                eprintln!("Infinite loop of IO operations detected");
-                process::exit(1)
-            }
+            _ => Err(BfError::InfiniteIoLoop),
         }
     }
 
-    fn multiple_loop_expr_optimize(mut exprs: Vec<Expr>) -> Expr {
+    fn multiple_loop_expr_optimize(mut exprs: Vec<Expr>) -> Result<Expr, BfError> {
         if exprs.len() < 3 {
-            return Loop(exprs);
+            return Ok(Loop(exprs));
         }
 
         let mut i = 0;
@@ -149,14 +98,14 @@ impl Parser {
             match &exprs[i..i + 3] {
                 [MoveLeftCount(x), DecrementCount(n), MoveRightCount(y)] if x == y => {
                     let new_op = OffsetOp {
-                        o: (0 - x) as i32,
-                        v: (0 - n) as i32,
+                        o: -(*x as i32),
+                        v: -(*n as i32),
                     };
                     exprs.splice(i..i + 3, [new_op]);
                 }
                 [MoveLeftCount(x), IncrementCount(n), MoveRightCount(y)] if x == y => {
                     let new_op = OffsetOp {
-                        o: (0 - x) as i32,
+                        o: -(*x as i32),
                         v: *n as i32,
                     };
                     exprs.splice(i..i + 3, [new_op]);
@@ -164,7 +113,7 @@ impl Parser {
                 [MoveRightCount(x), DecrementCount(n), MoveLeftCount(y)] if x == y => {
                     let new_op = OffsetOp {
                         o: *x as i32,
-                        v: (0 - n) as i32,
+                        v: -(*n as i32),
                     };
                     exprs.splice(i..i + 3, [new_op]);
                 }
@@ -179,26 +128,84 @@ impl Parser {
             }
             i += 1;
         }
-        Loop(exprs)
+        Ok(Loop(exprs))
     }
 
     // #[inline(always)]
-    fn optimize(exprs: Vec<Expr>) -> Expr {
-        let e = Self::single_loop_expr_optimize(exprs);
+    fn optimize(exprs: Vec<Expr>) -> Result<Expr, BfError> {
+        let e = Self::single_loop_expr_optimize(exprs)?;
         if let Loop(exprs) = e {
-            match <[Expr; 2]>::try_from(exprs) {
-                Ok([DecrementCount(1), OffsetOp { o, v }]) => OffsetMakeZeroOp { o, v },
-                Ok([OffsetOp { o, v }, DecrementCount(1)]) => OffsetMakeZeroOp { o, v },
-                Ok(arr) => Loop(arr.into()),
-                Err(exprs) => Loop(exprs),
+            match Self::try_multiply_loop(&exprs) {
+                Some(targets) => Ok(MultiplyOp { targets }),
+                None => Ok(Loop(exprs)),
             }
         } else {
-            e
+            Ok(e)
+        }
+    }
+
+    // Detects a balanced "multiply loop": pure arithmetic (no I/O, no nested
+    // loops), net pointer movement of zero, and a net delta of exactly -1 at
+    // offset 0 (so the loop is guaranteed to terminate by zeroing its own
+    // cell). Returns the per-offset deltas to apply, excluding offset 0.
+    // Negative target offsets are allowed (e.g. `[-<+>]`): whether one
+    // crosses the tape origin can only be known at runtime, against the
+    // actual pointer value, so that bounds check is deferred to MultiplyOp's
+    // execution rather than rejected here.
+    fn try_multiply_loop(exprs: &[Expr]) -> Option<Vec<(i32, i32)>> {
+        let mut pointer: i32 = 0;
+        let mut deltas: Vec<(i32, i32)> = Vec::new();
+        for expr in exprs {
+            match expr {
+                IncrementCount(n) => Self::add_delta(&mut deltas, pointer, *n as i32),
+                DecrementCount(n) => Self::add_delta(&mut deltas, pointer, -(*n as i32)),
+                MoveRightCount(n) => pointer += *n as i32,
+                MoveLeftCount(n) => pointer -= *n as i32,
+                OffsetOp { o, v } => Self::add_delta(&mut deltas, pointer + o, *v),
+                _ => return None,
+            }
+        }
+        if pointer != 0 {
+            return None;
+        }
+        match deltas.iter().find(|(o, _)| *o == 0) {
+            Some((_, -1)) => {}
+            _ => return None,
+        }
+        Some(deltas.into_iter().filter(|(o, _)| *o != 0).collect())
+    }
+
+    fn add_delta(deltas: &mut Vec<(i32, i32)>, offset: i32, delta: i32) {
+        match deltas.iter_mut().find(|(o, _)| *o == offset) {
+            Some(entry) => entry.1 += delta,
+            None => deltas.push((offset, delta)),
         }
     }
 
-    pub fn parse(&mut self) -> Vec<Expr> {
+    pub fn parse(&mut self) -> Result<Vec<Expr>, BfError> {
         let tokens = Token::from_char(self.source.chars());
         Expr::from_tokens(tokens)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multiply_loop_allows_negative_target_offsets() {
+        // Body of `[-<+>]`: copies the current cell one slot to the left.
+        let body = vec![DecrementCount(1), OffsetOp { o: -1, v: 1 }];
+        assert_eq!(Parser::try_multiply_loop(&body), Some(vec![(-1, 1)]));
+    }
+
+    #[test]
+    fn parses_left_copy_loop_without_underflowing() {
+        // Regression test: `multiple_loop_expr_optimize` used to compute
+        // `(0 - x) as i32`, underflowing the unsigned `x` before the cast,
+        // for any loop whose peephole match moves left before right.
+        let mut parser = Parser::new("[-<+>]".into());
+        let exprs = parser.parse().unwrap();
+        assert_eq!(exprs, vec![MultiplyOp { targets: vec![(-1, 1)] }]);
+    }
+}
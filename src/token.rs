@@ -1,4 +1,5 @@
-use std::str::Chars;
+use alloc::vec::Vec;
+use core::str::Chars;
 
 #[derive(PartialEq, Debug)]
 pub enum Token {